@@ -0,0 +1,50 @@
+//! Compares `emergence::parse::fast_ints` with the usual `str::parse` loop.
+//!
+//! Run with `cargo bench --bench parse`.
+
+use std::{hint::black_box, time::Instant};
+
+use emergence::parse::fast_ints;
+
+/// An input shaped like a typical list-of-numbers puzzle: a few thousand lines of
+/// comma-separated signed numbers
+fn input() -> String {
+    let mut state = 1u64;
+    let mut next = || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) as i64 % 20_000 - 10_000
+    };
+    (0..5_000)
+        .map(|_| format!("{},{},{},{}\n", next(), next(), next(), next()))
+        .collect()
+}
+
+fn str_parse(input: &str) -> i64 {
+    input
+        .lines()
+        .flat_map(|line| line.split(','))
+        .map(|n| n.parse::<i64>().unwrap())
+        .sum()
+}
+
+fn fast(input: &str) -> i64 {
+    fast_ints(input).sum()
+}
+
+fn bench(name: &str, input: &str, f: fn(&str) -> i64) {
+    const RUNS: u32 = 200;
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        black_box(f(black_box(input)));
+    }
+    println!("{name:>10}: {:?} per run", start.elapsed() / RUNS);
+}
+
+fn main() {
+    let input = input();
+    assert_eq!(str_parse(&input), fast(&input));
+    bench("str::parse", &input, str_parse);
+    bench("fast_ints", &input, fast);
+}
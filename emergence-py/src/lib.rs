@@ -0,0 +1,82 @@
+//! Python bindings for emergence, so Python solutions can share the same input cache as Rust
+//! ones.
+//!
+//! ```python
+//! from emergence import AoC
+//!
+//! aoc = AoC(2020)
+//! text = aoc.read_or_fetch(1)
+//! ```
+
+use std::path::PathBuf;
+
+use pyo3::{create_exception, exceptions::PyException, prelude::*};
+
+create_exception!(emergence, EmergenceError, PyException);
+
+fn to_py(e: emergence::Error) -> PyErr {
+    EmergenceError::new_err(e.to_string())
+}
+
+/// The main entry point, wrapping `emergence::AoC`
+#[pyclass(name = "AoC", frozen)]
+struct AoC(emergence::AoC);
+
+#[pymethods]
+impl AoC {
+    /// Construct a new AoC instance for `year`.
+    ///
+    /// Without a path, the cache lives in `~/.aoc`. Without a token, it is read from `$TOKEN`,
+    /// `$TOKEN_FILE`, or a `tokenfile` in the current directory or a parent.
+    #[new]
+    #[pyo3(signature = (year, path=None, token=None))]
+    fn new(year: usize, path: Option<PathBuf>, token: Option<String>) -> PyResult<Self> {
+        let aoc = match (path, token) {
+            (None, None) => emergence::AoC::new(year),
+            (Some(path), None) => emergence::AoC::with_path(year, path),
+            (Some(path), Some(token)) => emergence::AoC::with_path_and_token(year, path, token),
+            (None, Some(_)) => {
+                return Err(EmergenceError::new_err(
+                    "a cache path must be given along with a token",
+                ));
+            }
+        };
+        aoc.map(Self).map_err(to_py)
+    }
+
+    /// Read the input for `day` from the cache, or if it is not present, fetch it from Advent
+    /// of Code
+    fn read_or_fetch(&self, py: Python<'_>, day: usize) -> PyResult<String> {
+        py.detach(|| self.0.read_or_fetch(day)).map_err(to_py)
+    }
+
+    /// Fetch the input for `day` even if it is already cached. Fails if it differs from the
+    /// cached copy, unless `overwrite` is set
+    #[pyo3(signature = (day, overwrite=false))]
+    fn refetch(&self, py: Python<'_>, day: usize, overwrite: bool) -> PyResult<String> {
+        py.detach(|| self.0.refetch(day, overwrite)).map_err(to_py)
+    }
+
+    /// The time at which the puzzle for `day` unlocks, in RFC 3339 format
+    fn unlock_time(&self, day: usize) -> PyResult<String> {
+        self.0
+            .unlock_time(day)
+            .map(|t| t.to_rfc3339())
+            .map_err(to_py)
+    }
+
+    /// A line diff between the cached input for `day` and `other`, in the style of `diff`
+    fn diff_input(&self, py: Python<'_>, day: usize, other: &str) -> PyResult<String> {
+        py.detach(|| self.0.diff_input(day, other))
+            .map(|diff| diff.to_string())
+            .map_err(to_py)
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "emergence")]
+fn emergence_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<AoC>()?;
+    m.add("EmergenceError", m.py().get_type::<EmergenceError>())?;
+    Ok(())
+}
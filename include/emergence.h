@@ -0,0 +1,42 @@
+/*
+ * C interface to emergence, enabled with the `capi` feature. See src/capi.rs for documentation.
+ * Build the library with `cargo rustc --lib --release --features capi --crate-type cdylib`.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+#ifndef EMERGENCE_H
+#define EMERGENCE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define EMERGENCE_OK 0
+#define EMERGENCE_INVALID_ARGUMENT -1
+#define EMERGENCE_BUFFER_TOO_SMALL -2
+#define EMERGENCE_INVALID_DATE -3
+#define EMERGENCE_NOT_YET_RELEASED -4
+#define EMERGENCE_THROTTLED -5
+#define EMERGENCE_NETWORK -6
+#define EMERGENCE_IO -7
+#define EMERGENCE_OTHER -8
+#define EMERGENCE_UNAUTHORIZED -9
+/* The date is valid, but there is no puzzle for it, e.g. the provider returned 404 */
+#define EMERGENCE_PUZZLE_NOT_FOUND -10
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+int emergence_read_or_fetch(uint32_t year, uint32_t day, const char *path, const char *token,
+                            char *buf, size_t buf_len, size_t *out_len);
+
+const char *emergence_strerror(int code);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
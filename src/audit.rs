@@ -0,0 +1,132 @@
+use std::{fmt::Write as _, fs::File, io::Write, path::Path, sync::Mutex, time::Duration};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::Error;
+
+/// An append-only log of every request made to Advent of Code, one JSON object per line. Set with
+/// [`AoC::audit_log`](crate::AoC::audit_log)
+///
+/// Each line records when the request was made, its method, URL and day (if it was about one),
+/// the response status or error, how long it took, and how many times it had already been
+/// retried. Headers are never logged, so the session token never appears:
+///
+/// ```text
+/// {"time":"2022-12-01T05:00:01.234Z","method":"GET","url":"https://adventofcode.com/2022/day/1/input","day":1,"status":200,"error":null,"duration_ms":152,"retry":0}
+/// ```
+pub struct AuditLog {
+    file: Mutex<File>,
+}
+
+impl AuditLog {
+    /// Log to the file at `path`, appending if it already exists
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub(crate) fn record(&self, entry: &AuditEntry<'_>) {
+        let line = entry.to_json();
+        // An audit log that can't be written shouldn't stop the request from succeeding
+        let _ = self.file.lock().unwrap().write_all(line.as_bytes());
+    }
+}
+
+/// A single request, as recorded in an [`AuditLog`]
+pub(crate) struct AuditEntry<'a> {
+    pub(crate) time: DateTime<Utc>,
+    pub(crate) method: &'a str,
+    pub(crate) url: &'a str,
+    pub(crate) outcome: Result<u16, &'a Error>,
+    pub(crate) duration: Duration,
+    /// The number of earlier attempts at the same request
+    pub(crate) retry: u32,
+}
+
+impl AuditEntry<'_> {
+    /// The entry as a line of JSON, including the trailing newline
+    fn to_json(&self) -> String {
+        let mut json = format!(
+            r#"{{"time":{},"method":{},"url":{},"day":"#,
+            string(&self.time.to_rfc3339_opts(SecondsFormat::Millis, true)),
+            string(self.method),
+            string(self.url),
+        );
+        match day(self.url) {
+            Some(day) => write!(json, "{day}"),
+            None => write!(json, "null"),
+        }
+        .unwrap();
+        match &self.outcome {
+            Ok(status) => write!(json, r#","status":{status},"error":null"#),
+            Err(e) => write!(json, r#","status":null,"error":{}"#, string(&e.to_string())),
+        }
+        .unwrap();
+        writeln!(
+            json,
+            r#","duration_ms":{},"retry":{}}}"#,
+            self.duration.as_millis(),
+            self.retry
+        )
+        .unwrap();
+        json
+    }
+}
+
+/// The day a URL like `https://adventofcode.com/2022/day/1/input` is about
+fn day(url: &str) -> Option<usize> {
+    let (_, rest) = url.split_once("/day/")?;
+    rest.split(['/', '?', '#']).next()?.parse().ok()
+}
+
+/// `s` as a JSON string literal
+fn string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_json() {
+        let time = "2022-12-01T05:00:01.234Z".parse().unwrap();
+        let entry = AuditEntry {
+            time,
+            method: "GET",
+            url: "https://adventofcode.com/2022/day/1/input",
+            outcome: Ok(200),
+            duration: Duration::from_millis(152),
+            retry: 0,
+        };
+        assert_eq!(
+            entry.to_json(),
+            r#"{"time":"2022-12-01T05:00:01.234Z","method":"GET","url":"https://adventofcode.com/2022/day/1/input","day":1,"status":200,"error":null,"duration_ms":152,"retry":0}"#.to_owned() + "\n"
+        );
+
+        let error = Error::Git("a \"quoted\"\nerror".into());
+        let entry = AuditEntry {
+            url: "https://adventofcode.com/2022",
+            outcome: Err(&error),
+            retry: 2,
+            ..entry
+        };
+        assert!(entry.to_json().contains(
+            r#""day":null,"status":null,"error":"a \"quoted\"\nerror","duration_ms":152,"retry":2}"#
+        ));
+    }
+}
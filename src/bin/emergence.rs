@@ -0,0 +1,174 @@
+//! A command-line interface to the input cache, for solutions that aren't written in Rust.
+//!
+//! ```text
+//! emergence cat <day> [--year <year>]
+//! emergence doctor [--year <year>]
+//! emergence new <path> [--name <name>] [--year <year>]
+//! ```
+//!
+//! `new` generates a solutions crate with [`emergence::scaffold::generate`], named after the
+//! directory unless `--name` is given.
+
+use std::{io::Write, path::Path, process::ExitCode};
+
+use chrono::{Datelike, Duration, Utc};
+use emergence::{AoC, Error};
+
+const USAGE: &str = "Usage: emergence cat <day> [--year <year>]
+       emergence doctor [--year <year>]
+       emergence new <path> [--name <name>] [--year <year>]";
+
+fn main() -> ExitCode {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("emergence: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (command, args) = args.split_first().ok_or(USAGE)?;
+    let mut args: Vec<_> = args.iter().map(String::as_str).collect();
+    let year = take_option(&mut args, "--year")?
+        .map(|year| year.parse().map_err(|_| USAGE))
+        .transpose()?;
+    let name = take_option(&mut args, "--name")?;
+    match (command.as_str(), &args[..], name) {
+        ("cat", [day], None) => cat(year, day.parse().map_err(|_| USAGE)?),
+        ("doctor", [], None) => doctor(year),
+        ("new", [path], name) => new(Path::new(path), name, year),
+        _ => Err(USAGE.into()),
+    }
+}
+
+/// Remove `flag` and the value after it from the arguments, returning the value
+fn take_option<'a>(args: &mut Vec<&'a str>, flag: &str) -> Result<Option<&'a str>, &'static str> {
+    let Some(i) = args.iter().position(|&arg| arg == flag) else {
+        return Ok(None);
+    };
+    if i + 1 == args.len() {
+        return Err(USAGE);
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Ok(Some(value))
+}
+
+/// The instance for `year`, or the most recent event if it is `None`
+fn aoc(year: Option<usize>) -> Result<AoC, Error> {
+    match year {
+        Some(year) => AoC::new(year),
+        None => AoC::current(),
+    }
+}
+
+/// Print the input for `day`, fetching it if it isn't cached
+fn cat(year: Option<usize>, day: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let input = aoc(year)?.read_or_fetch(day)?;
+    // A closed pipe, e.g. from `head`, isn't worth reporting
+    let _ = std::io::stdout().lock().write_all(input.as_bytes());
+    Ok(())
+}
+
+/// Generate a solutions crate in `path`, for the most recent event if `year` is `None`
+fn new(
+    path: &Path,
+    name: Option<&str>,
+    year: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let name = match name {
+        Some(name) => name,
+        None => path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or("Can't name the crate after the path, pass --name")?,
+    };
+    let year = year.unwrap_or_else(|| {
+        // Each event starts at midnight EST on the 1st of December
+        let now = Utc::now() - Duration::hours(5);
+        if now.month() == 12 {
+            now.year() as usize
+        } else {
+            now.year() as usize - 1
+        }
+    });
+    emergence::scaffold::generate(path, name, year)?;
+    println!(
+        "Created {name} for Advent of Code {year} in {}",
+        path.display()
+    );
+    Ok(())
+}
+
+/// Check that everything needed to fetch inputs is set up, and suggest fixes for anything that
+/// isn't
+fn doctor(year: Option<usize>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut problems = 0;
+    let mut problem = |what: String, fix: &str| {
+        println!("✗ {what}\n  {fix}");
+        problems += 1;
+    };
+
+    let Some(source) = AoC::token_source()? else {
+        problem(
+            "No session token found".into(),
+            "Set $TOKEN, point $TOKEN_FILE at a file containing it, or put it in a file called `tokenfile` in this directory",
+        );
+        return Err("1 problem found".into());
+    };
+    println!("✓ Token found in {source}");
+
+    let aoc = aoc(year)?;
+    let diagnostics = aoc.diagnostics();
+    println!("✓ emergence {}", diagnostics.version);
+    if diagnostics.cache_mode.is_some_and(|mode| mode & 0o200 == 0) {
+        problem(
+            format!(
+                "The cache at {} is read-only",
+                diagnostics.cache_path.display()
+            ),
+            "Make it writable, e.g. with `chmod u+w`",
+        );
+    } else {
+        println!("✓ Cache at {}", diagnostics.cache_path.display());
+    }
+
+    match aoc.probe(1) {
+        Ok(()) => println!("✓ Advent of Code accepted the token"),
+        Err(Error::Unauthorized) => problem(
+            "Advent of Code did not accept the token".into(),
+            "It has probably expired. Log in again and copy the new `session` cookie",
+        ),
+        Err(e) => problem(
+            format!("Could not reach Advent of Code: {e}"),
+            "Check your network connection, and $HTTPS_PROXY and $NO_PROXY if you use a proxy",
+        ),
+    }
+
+    match aoc.clock_skew() {
+        Ok(Some(skew)) if skew.num_seconds().abs() > 5 => problem(
+            format!(
+                "The system clock is {}s {} Advent of Code's",
+                skew.num_seconds().abs(),
+                if skew.num_seconds() > 0 {
+                    "behind"
+                } else {
+                    "ahead of"
+                }
+            ),
+            "Puzzles will seem to unlock at the wrong time. Enable time synchronisation (NTP)",
+        ),
+        Ok(Some(_)) => println!("✓ The system clock is accurate"),
+        // Unreachable servers have already been reported
+        Ok(None) | Err(_) => {}
+    }
+
+    match problems {
+        0 => Ok(()),
+        1 => Err("1 problem found".into()),
+        n => Err(format!("{n} problems found").into()),
+    }
+}
@@ -0,0 +1,301 @@
+//! Versioning of the on-disk cache layout.
+//!
+//! The root of every cache contains a `manifest` file recording the version of the layout it was
+//! written with. When an older cache is opened, the migrations between its version and
+//! [`VERSION`] are run in order to upgrade it in place.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, Fs};
+
+/// The version of the cache layout written by this version of emergence
+pub(crate) const VERSION: u32 = 1;
+
+/// Upgrades the cache at the given root by one version
+type Migration = fn(&dyn Fs, &Path) -> io::Result<()>;
+
+/// `MIGRATIONS[n]` upgrades a cache from version `n` to version `n + 1`
+const MIGRATIONS: [Migration; VERSION as usize] = [
+    // Version 0 is the layout from before the manifest existed, which is identical to version 1
+    |_, _| Ok(()),
+];
+
+/// Read the layout version from the manifest at `root`. Caches without a manifest are version 0
+fn read_version(fs: &dyn Fs, root: &Path) -> Result<u32, Error> {
+    let Some(manifest) = fs.read(&root.join("manifest"))? else {
+        return Ok(0);
+    };
+    manifest
+        .lines()
+        .find_map(|line| line.strip_prefix("version="))
+        .and_then(|v| v.trim().parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "malformed cache manifest").into()
+        })
+}
+
+fn write_version(fs: &dyn Fs, root: &Path, version: u32) -> io::Result<()> {
+    fs.write(&root.join("manifest"), &format!("version={version}\n"))
+}
+
+/// Bring the cache at `root` up to date, creating its manifest if necessary
+pub(crate) fn migrate(fs: &dyn Fs, root: &Path) -> Result<(), Error> {
+    let version = read_version(fs, root)?;
+    if version > VERSION {
+        return Err(Error::UnsupportedCacheVersion(version));
+    }
+
+    // Record progress after each step, so an interrupted upgrade resumes where it left off
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(fs, root)?;
+        write_version(fs, root, from as u32 + 1)?;
+    }
+    Ok(())
+}
+
+/// The directory under the cache root holding snapshots
+pub(crate) const SNAPSHOTS: &str = "snapshots";
+
+/// The end of the names of files holding notes, which sit next to the inputs they are about
+pub(crate) const NOTES_SUFFIX: &str = ".notes.md";
+
+/// Entries at the root of the cache that are never snapshotted or restored: the snapshots
+/// themselves, the git-sync history, the cookie jar, and the directories used while restoring
+const UNSNAPSHOTTED: [&str; 5] = [SNAPSHOTS, ".git", "cookies", RESTORE_NEW, RESTORE_OLD];
+
+/// Where a snapshot is copied to before it replaces the cache
+const RESTORE_NEW: &str = ".restore-new";
+
+/// Where the cache is moved to while it is replaced by a snapshot
+const RESTORE_OLD: &str = ".restore-old";
+
+/// The names of the entries in `dir` that snapshots are made of
+fn snapshotted(dir: &Path) -> io::Result<Vec<std::ffi::OsString>> {
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        if !UNSNAPSHOTTED.iter().any(|skip| name == *skip) {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+/// Recursively copy everything in `from` into `to`, skipping the root entries in `UNSNAPSHOTTED`
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fn copy_all(from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_entry(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    }
+
+    fn copy_entry(from: &Path, to: &Path) -> io::Result<()> {
+        if std::fs::metadata(from)?.is_dir() {
+            copy_all(from, to)
+        } else {
+            std::fs::copy(from, to).map(|_| ())
+        }
+    }
+
+    std::fs::create_dir_all(to)?;
+    for name in snapshotted(from)? {
+        copy_entry(&from.join(&name), &to.join(&name))?;
+    }
+    Ok(())
+}
+
+/// Move the entries called `names` from `from` into `to`
+fn move_entries(names: &[std::ffi::OsString], from: &Path, to: &Path) -> io::Result<()> {
+    for name in names {
+        std::fs::rename(from.join(name), to.join(name))?;
+    }
+    Ok(())
+}
+
+/// Remove the directory at `path` if it exists
+fn remove_dir(path: &Path) -> io::Result<()> {
+    match std::fs::remove_dir_all(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Copy the whole cache at `root` into a new snapshot called `name`
+pub(crate) fn snapshot(root: &Path, name: &str) -> io::Result<PathBuf> {
+    let dest = root.join(SNAPSHOTS).join(name);
+    if dest.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("snapshot {} already exists", dest.display()),
+        ));
+    }
+    copy_dir(root, &dest)?;
+    Ok(dest)
+}
+
+/// List the snapshots of the cache at `root`, oldest first
+pub(crate) fn snapshots(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut snapshots = match std::fs::read_dir(root.join(SNAPSHOTS)) {
+        Ok(dir) => dir
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+/// Replace the contents of the cache at `root` with the snapshot at `snapshot`, which must be one
+/// of its own snapshots
+///
+/// The snapshot is copied in full before anything in the cache is touched, so a failed copy leaves
+/// the cache as it was. The git-sync history and cookie jar are kept.
+pub(crate) fn restore(root: &Path, snapshot: &Path) -> io::Result<()> {
+    let not_a_snapshot = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a snapshot of this cache", snapshot.display()),
+        )
+    };
+    let snapshot = snapshot.canonicalize().map_err(|_| not_a_snapshot())?;
+    if snapshot.parent() != Some(&*root.join(SNAPSHOTS).canonicalize()?)
+        || !snapshot.join("manifest").is_file()
+    {
+        return Err(not_a_snapshot());
+    }
+
+    let new = root.join(RESTORE_NEW);
+    let old = root.join(RESTORE_OLD);
+    // Left over from an interrupted restore
+    remove_dir(&new)?;
+    remove_dir(&old)?;
+    if let Err(e) = copy_dir(&snapshot, &new) {
+        remove_dir(&new)?;
+        return Err(e);
+    }
+
+    std::fs::create_dir(&old)?;
+    let current = snapshotted(root)?;
+    let swapped = move_entries(&current, root, &old)
+        .and_then(|()| move_entries(&snapshotted(&new)?, &new, root));
+    if let Err(e) = swapped {
+        // Put back whatever was moved aside. What can't be put back is still in `old`
+        let moved = snapshotted(&old)?;
+        for name in snapshotted(root)? {
+            if moved.contains(&name) {
+                continue;
+            }
+            let path = root.join(&name);
+            if path.is_dir() {
+                std::fs::remove_dir_all(path)?;
+            } else {
+                std::fs::remove_file(path)?;
+            }
+        }
+        move_entries(&moved, &old, root)?;
+        remove_dir(&old)?;
+        remove_dir(&new)?;
+        return Err(e);
+    }
+    remove_dir(&old)?;
+    remove_dir(&new)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::{MemoryFs, OsFs};
+
+    #[test]
+    fn versions() {
+        let fs = MemoryFs::new();
+        let root = Path::new("cache");
+        assert_eq!(read_version(&fs, root).unwrap(), 0);
+        migrate(&fs, root).unwrap();
+        assert_eq!(read_version(&fs, root).unwrap(), VERSION);
+
+        write_version(&fs, root, VERSION + 1).unwrap();
+        assert!(matches!(
+            migrate(&fs, root),
+            Err(Error::UnsupportedCacheVersion(v)) if v == VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn snapshot_restore() {
+        let dir = TempDir::new("emergence").unwrap();
+        let root = dir.path();
+        migrate(&OsFs, root).unwrap();
+        std::fs::create_dir(root.join("2020")).unwrap();
+        std::fs::write(root.join("2020/day01.txt"), "hello").unwrap();
+
+        let snap = snapshot(root, "a").unwrap();
+        assert_eq!(snapshots(root).unwrap(), std::slice::from_ref(&snap));
+        assert!(snapshot(root, "a").is_err());
+
+        std::fs::write(root.join("2020/day01.txt"), "goodbye").unwrap();
+        std::fs::write(root.join("2020/day02.txt"), "world").unwrap();
+        restore(root, &snap).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(root.join("2020/day01.txt")).unwrap(),
+            "hello"
+        );
+        assert!(!root.join("2020/day02.txt").exists());
+        assert!(snap.is_dir());
+        assert_eq!(snapshotted(root).unwrap(), snapshotted(&snap).unwrap());
+    }
+
+    #[test]
+    fn restore_keeps_history() {
+        let dir = TempDir::new("emergence").unwrap();
+        let root = dir.path();
+        migrate(&OsFs, root).unwrap();
+        std::fs::create_dir(root.join(".git")).unwrap();
+        std::fs::write(root.join(".git/HEAD"), "old").unwrap();
+        std::fs::write(root.join("cookies"), "ru=old\n").unwrap();
+
+        let snap = snapshot(root, "a").unwrap();
+        assert!(!snap.join(".git").exists());
+        assert!(!snap.join("cookies").exists());
+
+        std::fs::write(root.join(".git/HEAD"), "new").unwrap();
+        std::fs::write(root.join("cookies"), "ru=new\n").unwrap();
+        restore(root, &snap).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root.join(".git/HEAD")).unwrap(),
+            "new"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("cookies")).unwrap(),
+            "ru=new\n"
+        );
+    }
+
+    #[test]
+    fn restore_only_snapshots() {
+        let dir = TempDir::new("emergence").unwrap();
+        let root = dir.path().join("cache");
+        std::fs::create_dir_all(root.join("2020")).unwrap();
+        migrate(&OsFs, &root).unwrap();
+        std::fs::write(root.join("2020/day01.txt"), "hello").unwrap();
+
+        // Neither the cache itself nor another cache is one of its snapshots
+        let other = dir.path().join("other");
+        std::fs::create_dir(&other).unwrap();
+        migrate(&OsFs, &other).unwrap();
+        assert!(restore(&root, &root).is_err());
+        assert!(restore(&root, &other).is_err());
+        assert!(restore(&root, &root.join(SNAPSHOTS).join("missing")).is_err());
+        assert!(root.join("2020/day01.txt").exists());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::html;
+
+/// How many stars have been collected for a day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stars {
+    #[default]
+    None,
+    One,
+    Two,
+}
+
+impl Stars {
+    /// The number of stars, as a number
+    pub fn count(self) -> usize {
+        self as usize
+    }
+}
+
+/// A year's calendar page, as returned by [`AoC::calendar`](crate::AoC::calendar)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Calendar {
+    pub year: usize,
+    /// The stars collected for each released day. `days[0]` is day 1; days that have not been
+    /// released yet are not present
+    pub days: Vec<Stars>,
+    /// The calendar's ASCII art, as plain text
+    pub art: String,
+}
+
+impl Calendar {
+    /// Parse a calendar from the HTML of `https://adventofcode.com/{year}`
+    pub(crate) fn parse(year: usize, page: &str) -> Self {
+        let body = html::element(page, "<pre class=\"calendar", "</pre>").unwrap_or_default();
+
+        // Released days are links labelled e.g. "Day 1, two stars". Unreleased days are plain
+        // spans with no label
+        let mut days = Vec::new();
+        for tag in body.split("<a ").skip(1) {
+            let Some(label) = html::attr(tag, "aria-label") else {
+                continue;
+            };
+            let Some((day, stars)) = parse_label(label) else {
+                continue;
+            };
+            if days.len() < day {
+                days.resize(day, Stars::None);
+            }
+            days[day - 1] = stars;
+        }
+
+        Self {
+            year,
+            days,
+            art: html::text(body),
+        }
+    }
+
+    /// The stars collected for `day`. Unreleased days have no stars
+    pub fn stars(&self, day: usize) -> Stars {
+        day.checked_sub(1)
+            .and_then(|i| self.days.get(i))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The total number of stars collected this year
+    pub fn total_stars(&self) -> usize {
+        self.days.iter().map(|s| s.count()).sum()
+    }
+}
+
+/// Parse an `aria-label` like `Day 12, one star` into the day and stars
+fn parse_label(label: &str) -> Option<(usize, Stars)> {
+    let label = label.strip_prefix("Day ")?;
+    let (day, stars) = label.split_once(',').unwrap_or((label, ""));
+    let day = day.trim().parse().ok().filter(|&d| d > 0)?;
+    let stars = match stars.trim() {
+        "two stars" => Stars::Two,
+        "one star" => Stars::One,
+        _ => Stars::None,
+    };
+    Some((day, stars))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<main>
+<pre class="calendar"><span aria-hidden="true" class="calendar-day4">   ~ ~   </span>
+<a aria-label="Day 1, two stars" href="/2020/day/1" class="calendar-day1 calendar-verycomplete"><span class="calendar-color-w">..##..</span>  <span class="calendar-day"> 1</span> <span class="calendar-mark-complete">*</span><span class="calendar-mark-verycomplete">*</span></a>
+<a aria-label="Day 2, one star" href="/2020/day/2" class="calendar-day2 calendar-complete"><span>.&gt;..</span>  <span class="calendar-day"> 2</span></a>
+<a aria-label="Day 3" href="/2020/day/3" class="calendar-day3"><span>....</span>  <span class="calendar-day"> 3</span></a>
+</pre>
+</main>"#;
+
+    #[test]
+    fn parse() {
+        let calendar = Calendar::parse(2020, PAGE);
+        assert_eq!(calendar.days, [Stars::Two, Stars::One, Stars::None]);
+        assert_eq!(calendar.stars(1), Stars::Two);
+        assert_eq!(calendar.stars(25), Stars::None);
+        assert_eq!(calendar.total_stars(), 3);
+        assert!(calendar.art.contains("..##..   1 **"));
+        assert!(calendar.art.contains(".>..   2"));
+    }
+}
@@ -0,0 +1,212 @@
+//! A minimal C interface, so solutions in other languages can share emergence's cache.
+//!
+//! The C library isn't built by default, so that depending on emergence from Rust doesn't also
+//! build one. Build it with
+//!
+//! ```text
+//! cargo rustc --lib --release --features capi --crate-type cdylib
+//! ```
+//!
+//! and use it with `include/emergence.h`:
+//!
+//! ```c
+//! char buf[65536];
+//! size_t len;
+//! int err = emergence_read_or_fetch(2020, 1, NULL, NULL, buf, sizeof buf, &len);
+//! if (err != EMERGENCE_OK) {
+//!     fprintf(stderr, "%s\n", emergence_strerror(err));
+//! }
+//! ```
+
+use std::{
+    ffi::{CStr, c_char, c_int},
+    panic::{AssertUnwindSafe, catch_unwind},
+};
+
+use crate::{AoC, Error};
+
+pub const EMERGENCE_OK: c_int = 0;
+/// A pointer argument was null when it shouldn't be, or a string was not valid UTF-8
+pub const EMERGENCE_INVALID_ARGUMENT: c_int = -1;
+/// The output buffer was too small. The required length is written to `out_len`
+pub const EMERGENCE_BUFFER_TOO_SMALL: c_int = -2;
+/// The day was 0 or after the 25th, or the year had no event
+pub const EMERGENCE_INVALID_DATE: c_int = -3;
+/// The puzzle has not been released yet
+pub const EMERGENCE_NOT_YET_RELEASED: c_int = -4;
+/// Advent of Code is rate limiting requests
+pub const EMERGENCE_THROTTLED: c_int = -5;
+/// A request to Advent of Code failed
+pub const EMERGENCE_NETWORK: c_int = -6;
+/// Reading or writing the cache failed
+pub const EMERGENCE_IO: c_int = -7;
+/// Any other error, including a token not being found
+pub const EMERGENCE_OTHER: c_int = -8;
+/// Advent of Code did not accept the session token, which has usually expired
+pub const EMERGENCE_UNAUTHORIZED: c_int = -9;
+/// The date is valid, but there is no puzzle for it
+pub const EMERGENCE_PUZZLE_NOT_FOUND: c_int = -10;
+
+fn code(e: &Error) -> c_int {
+    match e {
+        Error::DayZero | Error::OutOfBounds | Error::InvalidYear(_) | Error::EventNotStarted(_) => {
+            EMERGENCE_INVALID_DATE
+        }
+        Error::NotYetReleased(_) => EMERGENCE_NOT_YET_RELEASED,
+        Error::Throttled => EMERGENCE_THROTTLED,
+        Error::Unauthorized => EMERGENCE_UNAUTHORIZED,
+        Error::PuzzleNotFound { .. } => EMERGENCE_PUZZLE_NOT_FOUND,
+        Error::Reqwest(_) | Error::Status { .. } | Error::Incomplete { .. } => EMERGENCE_NETWORK,
+        Error::Io(_) => EMERGENCE_IO,
+        _ => EMERGENCE_OTHER,
+    }
+}
+
+/// Read a nullable C string argument
+///
+/// # Safety
+///
+/// `s` must be null or a valid NUL-terminated string
+unsafe fn opt_str<'a>(s: *const c_char) -> Result<Option<&'a str>, c_int> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map(Some)
+        .map_err(|_| EMERGENCE_INVALID_ARGUMENT)
+}
+
+/// Read the input for `day` of `year` from the cache, fetching it if necessary, into `buf`.
+///
+/// `path` is the cache directory, or null for `~/.aoc`. `token` is the session token, or null
+/// to read it from `$TOKEN`, `$TOKEN_FILE` or a `tokenfile`. A token can only be given along
+/// with a path.
+///
+/// On success, the input is written to `buf` followed by a NUL, and its length (excluding the
+/// NUL) is written to `out_len`. Returns `EMERGENCE_OK` or one of the negative error codes.
+///
+/// # Safety
+///
+/// `path` and `token` must be null or valid NUL-terminated strings, `buf` must be valid for
+/// writes of `buf_len` bytes, and `out_len` must be valid for a write.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn emergence_read_or_fetch(
+    year: u32,
+    day: u32,
+    path: *const c_char,
+    token: *const c_char,
+    buf: *mut c_char,
+    buf_len: usize,
+    out_len: *mut usize,
+) -> c_int {
+    if buf.is_null() || out_len.is_null() {
+        return EMERGENCE_INVALID_ARGUMENT;
+    }
+    let (path, token) = match unsafe { (opt_str(path), opt_str(token)) } {
+        (Ok(path), Ok(token)) => (path, token),
+        _ => return EMERGENCE_INVALID_ARGUMENT,
+    };
+
+    // Token discovery panics on failure, and unwinding into C is undefined behaviour
+    let res = catch_unwind(AssertUnwindSafe(|| {
+        let aoc = match (path, token) {
+            (None, None) => AoC::new(year as _),
+            (Some(path), None) => AoC::with_path(year as _, path),
+            (Some(path), Some(token)) => AoC::with_path_and_token(year as _, path, token.into()),
+            (None, Some(_)) => return Err(EMERGENCE_INVALID_ARGUMENT),
+        };
+        aoc.and_then(|aoc| aoc.read_or_fetch(day as _))
+            .map_err(|e| code(&e))
+    }));
+    let input = match res {
+        Ok(Ok(input)) => input,
+        Ok(Err(code)) => return code,
+        Err(_) => return EMERGENCE_OTHER,
+    };
+
+    unsafe { *out_len = input.len() };
+    if input.len() >= buf_len {
+        return EMERGENCE_BUFFER_TOO_SMALL;
+    }
+    unsafe {
+        std::ptr::copy_nonoverlapping(input.as_ptr(), buf.cast(), input.len());
+        *buf.add(input.len()) = 0;
+    }
+    EMERGENCE_OK
+}
+
+/// A static, human-readable description of an error code
+#[unsafe(no_mangle)]
+pub extern "C" fn emergence_strerror(code: c_int) -> *const c_char {
+    let msg: &CStr = match code {
+        EMERGENCE_OK => c"success",
+        EMERGENCE_INVALID_ARGUMENT => c"invalid argument",
+        EMERGENCE_BUFFER_TOO_SMALL => c"buffer too small",
+        EMERGENCE_INVALID_DATE => c"no puzzle exists for that date",
+        EMERGENCE_NOT_YET_RELEASED => c"puzzle not yet released",
+        EMERGENCE_THROTTLED => c"rate limited by Advent of Code",
+        EMERGENCE_NETWORK => c"network error",
+        EMERGENCE_IO => c"cache i/o error",
+        EMERGENCE_UNAUTHORIZED => c"session token not accepted by Advent of Code",
+        EMERGENCE_PUZZLE_NOT_FOUND => c"no puzzle found for that date",
+        _ => c"unknown error",
+    };
+    msg.as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn read_cached() {
+        let dir = TempDir::new("emergence").unwrap();
+        let aoc = AoC::with_path_and_token(2020, dir.path(), "TOKEN".into()).unwrap();
+        aoc.write(1, "hello").unwrap();
+
+        let path = std::ffi::CString::new(dir.path().to_str().unwrap()).unwrap();
+        let mut buf = [0 as c_char; 6];
+        let mut len = 0;
+        let read = |buf: &mut [c_char], len: &mut usize, day| unsafe {
+            emergence_read_or_fetch(
+                2020,
+                day,
+                path.as_ptr(),
+                c"TOKEN".as_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                len,
+            )
+        };
+
+        assert_eq!(read(&mut buf, &mut len, 1), EMERGENCE_OK);
+        assert_eq!(len, 5);
+        assert_eq!(unsafe { CStr::from_ptr(buf.as_ptr()) }, c"hello");
+
+        assert_eq!(read(&mut buf[..5], &mut len, 1), EMERGENCE_BUFFER_TOO_SMALL);
+        assert_eq!(len, 5);
+        assert_eq!(read(&mut buf, &mut len, 26), EMERGENCE_INVALID_DATE);
+    }
+
+    #[test]
+    fn unauthorized() {
+        assert_eq!(code(&Error::Unauthorized), EMERGENCE_UNAUTHORIZED);
+        assert_eq!(
+            unsafe { CStr::from_ptr(emergence_strerror(EMERGENCE_UNAUTHORIZED)) },
+            c"session token not accepted by Advent of Code"
+        );
+    }
+
+    #[test]
+    fn puzzle_not_found() {
+        let e = Error::PuzzleNotFound { year: 2020, day: 3 };
+        assert_eq!(code(&e), EMERGENCE_PUZZLE_NOT_FOUND);
+        assert_eq!(
+            unsafe { CStr::from_ptr(emergence_strerror(EMERGENCE_PUZZLE_NOT_FOUND)) },
+            c"no puzzle found for that date"
+        );
+    }
+}
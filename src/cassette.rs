@@ -0,0 +1,210 @@
+use std::{fs::File, io::Write, path::Path, str::FromStr, sync::Mutex};
+
+use reqwest::{
+    StatusCode,
+    blocking::{Request, Response},
+    header::{HeaderMap, HeaderName, HeaderValue, SET_COOKIE},
+};
+
+use crate::{Error, Middleware, Next, middleware::Buffered};
+
+/// Middleware that records requests to Advent of Code and their responses to a cassette file, or
+/// replays them from one without touching the network.
+///
+/// This allows integration tests to run offline and deterministically against real responses.
+/// Only the method and URL of a request are matched when replaying, and interactions with the
+/// same method and URL are replayed in the order they were recorded. `Set-Cookie` headers are not
+/// recorded.
+///
+/// ```no_run
+/// # use emergence::{AoC, Cassette};
+/// // Records the first time this is run, and replays every time after that
+/// let aoc = AoC::with_path_and_token(2022, "cache", "token".into())?
+///     .middleware(Cassette::new("tests/cassettes/day1")?);
+/// # Ok::<(), emergence::Error>(())
+/// ```
+pub struct Cassette {
+    mode: Mode,
+}
+
+enum Mode {
+    Record(Mutex<File>),
+    Replay(Mutex<Vec<Interaction>>),
+}
+
+struct Interaction {
+    method: String,
+    url: String,
+    response: Buffered,
+}
+
+impl Cassette {
+    /// Replay from the cassette at `path` if it exists, otherwise record to it
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
+        if path.as_ref().exists() {
+            Self::replay(path)
+        } else {
+            Self::record(path)
+        }
+    }
+
+    /// Record to the cassette at `path`, replacing it if it already exists
+    pub fn record(path: impl AsRef<Path>) -> Result<Self, Error> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(Self {
+            mode: Mode::Record(Mutex::new(File::create(path)?)),
+        })
+    }
+
+    /// Replay from the cassette at `path`. Requests that were not recorded fail with
+    /// [`Error::NotRecorded`]
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let cassette = std::fs::read(path)?;
+        let interactions = parse(&cassette).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "malformed cassette")
+        })?;
+        Ok(Self {
+            mode: Mode::Replay(Mutex::new(interactions)),
+        })
+    }
+}
+
+impl Middleware for Cassette {
+    fn handle(&self, req: Request, next: Next<'_>) -> Result<Response, Error> {
+        let method = req.method().to_string();
+        let url = req.url().to_string();
+        match &self.mode {
+            Mode::Record(file) => {
+                let mut response = Buffered::read(next.run(req)?)?;
+                response.headers.remove(SET_COOKIE);
+                let interaction = Interaction {
+                    method,
+                    url,
+                    response,
+                };
+                file.lock().unwrap().write_all(&interaction.serialize())?;
+                Ok(interaction.response.into_response())
+            }
+            Mode::Replay(interactions) => {
+                let mut interactions = interactions.lock().unwrap();
+                let i = interactions
+                    .iter()
+                    .position(|i| i.method == method && i.url == url)
+                    .ok_or_else(|| Error::NotRecorded(format!("{method} {url}")))?;
+                Ok(interactions.remove(i).response.into_response())
+            }
+        }
+    }
+}
+
+impl Interaction {
+    /// An interaction is stored as the request line, the status, the headers, then the length of
+    /// the body followed by the body itself:
+    ///
+    /// ```text
+    /// GET https://adventofcode.com/2022/day/1/input
+    /// 200
+    /// content-type: text/plain
+    /// body 4
+    /// 1000
+    /// ```
+    fn serialize(&self) -> Vec<u8> {
+        let res = &self.response;
+        let mut out = format!("{} {}\n{}\n", self.method, self.url, res.status.as_u16());
+        for (name, value) in &res.headers {
+            out += &format!("{name}: {}\n", String::from_utf8_lossy(value.as_bytes()));
+        }
+        out += &format!("body {}\n", res.body.len());
+        let mut out = out.into_bytes();
+        out.extend_from_slice(&res.body);
+        out.extend_from_slice(b"\n\n");
+        out
+    }
+}
+
+/// Parse every interaction in a cassette, returning `None` if it is malformed
+fn parse(mut cassette: &[u8]) -> Option<Vec<Interaction>> {
+    let mut interactions = Vec::new();
+    while let Some(request) = line(&mut cassette) {
+        let (method, url) = request.split_once(' ')?;
+        let status = StatusCode::from_str(line(&mut cassette)?).ok()?;
+        let mut headers = HeaderMap::new();
+        let len = loop {
+            let line = line(&mut cassette)?;
+            if let Some(len) = line.strip_prefix("body ") {
+                break len.parse::<usize>().ok()?;
+            }
+            let (name, value) = line.split_once(": ")?;
+            headers.append(
+                HeaderName::from_str(name).ok()?,
+                HeaderValue::from_str(value).ok()?,
+            );
+        };
+        // The body may contain anything, so is taken by length rather than by lines
+        let body = cassette.get(..len)?.to_vec();
+        cassette = cassette.get(len..)?.strip_prefix(b"\n\n")?;
+        interactions.push(Interaction {
+            method: method.to_owned(),
+            url: url.to_owned(),
+            response: Buffered {
+                status,
+                headers,
+                body,
+            },
+        });
+    }
+    Some(interactions)
+}
+
+/// Take the next line from the start of `cassette`
+fn line<'a>(cassette: &mut &'a [u8]) -> Option<&'a str> {
+    let end = cassette.iter().position(|&b| b == b'\n')?;
+    let line = std::str::from_utf8(&cassette[..end]).ok()?;
+    *cassette = &cassette[end + 1..];
+    Some(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("text/plain"));
+        let interactions = [
+            Interaction {
+                method: "GET".into(),
+                url: "https://adventofcode.com/2020/day/1/input".into(),
+                response: Buffered {
+                    status: StatusCode::OK,
+                    headers,
+                    body: b"1721\n979\n\nbody 3\n".to_vec(),
+                },
+            },
+            Interaction {
+                method: "HEAD".into(),
+                url: "https://adventofcode.com/2020/day/2/input".into(),
+                response: Buffered {
+                    status: StatusCode::NOT_FOUND,
+                    headers: HeaderMap::new(),
+                    body: Vec::new(),
+                },
+            },
+        ];
+        let cassette: Vec<u8> = interactions.iter().flat_map(|i| i.serialize()).collect();
+
+        let parsed = parse(&cassette).unwrap();
+        assert_eq!(parsed.len(), 2);
+        for (a, b) in interactions.iter().zip(&parsed) {
+            assert_eq!(a.method, b.method);
+            assert_eq!(a.url, b.url);
+            assert_eq!(a.response.status, b.response.status);
+            assert_eq!(a.response.headers, b.response.headers);
+            assert_eq!(a.response.body, b.response.body);
+        }
+        assert!(parse(&cassette[..cassette.len() - 3]).is_none());
+    }
+}
@@ -0,0 +1,53 @@
+use std::{sync::Mutex, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use crate::Error;
+
+/// Stops requests being made for a while after too many consecutive failures, so a struggling
+/// adventofcode.com isn't hammered with retries
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    failures: u32,
+    open_until: Option<DateTime<Utc>>,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Fail with [`Error::CircuitOpen`] if requests are currently not allowed
+    pub(crate) fn check(&self, now: DateTime<Utc>) -> Result<(), Error> {
+        let state = self.state.lock().unwrap();
+        match state.open_until.map(|until| (until - now).to_std()) {
+            Some(Ok(remaining)) if !remaining.is_zero() => Err(Error::CircuitOpen(remaining)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record the outcome of a request. Once the circuit has tripped, a single failure after the
+    /// cooldown trips it again
+    pub(crate) fn record(&self, success: bool, now: DateTime<Utc>) {
+        let mut state = self.state.lock().unwrap();
+        if success {
+            *state = State::default();
+            return;
+        }
+        state.failures += 1;
+        if state.failures >= self.threshold {
+            state.open_until = Some(now + self.cooldown);
+        }
+    }
+}
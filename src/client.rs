@@ -0,0 +1,74 @@
+use std::{net::SocketAddr, time::Duration};
+
+use reqwest::{Certificate, blocking::Client};
+
+/// Tuning for the HTTP client's connections, set with
+/// [`AoC::connection_options`](crate::AoC::connection_options)
+///
+/// The defaults are reqwest's, except that compression is enabled. These are mostly of interest
+/// to long-running programs that make many requests, such as leaderboard pollers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionOptions {
+    /// Use HTTP/2 without negotiating it first
+    pub http2_prior_knowledge: bool,
+    /// How often to send TCP keep-alive probes on idle connections, if at all
+    pub tcp_keepalive: Option<Duration>,
+    /// Ask for responses to be compressed with gzip or brotli, and decompress them
+    pub compression: bool,
+    /// Set `TCP_NODELAY` on connections
+    pub tcp_nodelay: bool,
+    /// The maximum number of idle connections to keep open
+    pub pool_max_idle_per_host: usize,
+    /// How long to keep idle connections open for, or `None` to keep them indefinitely
+    pub pool_idle_timeout: Option<Duration>,
+    /// Resolve these domains to these addresses instead of using DNS, e.g. to point
+    /// `adventofcode.com` at a mock server in tests. The port of each address is ignored
+    pub resolve: Vec<(String, Vec<SocketAddr>)>,
+    /// PEM-encoded certificates to trust in addition to the default roots, e.g. for a
+    /// TLS-intercepting proxy
+    pub root_certificates: Vec<Vec<u8>>,
+    /// Use the proxies configured by `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY` (or their
+    /// lowercase versions), except for hosts listed in `NO_PROXY`. This is the default
+    pub system_proxy: bool,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        Self {
+            http2_prior_knowledge: false,
+            tcp_keepalive: None,
+            compression: true,
+            tcp_nodelay: true,
+            pool_max_idle_per_host: usize::MAX,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            resolve: Vec::new(),
+            root_certificates: Vec::new(),
+            system_proxy: true,
+        }
+    }
+}
+
+impl ConnectionOptions {
+    pub(crate) fn build(&self) -> reqwest::Result<Client> {
+        let mut builder = Client::builder()
+            .tcp_keepalive(self.tcp_keepalive)
+            .gzip(self.compression)
+            .brotli(self.compression)
+            .tcp_nodelay(self.tcp_nodelay)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(self.pool_idle_timeout);
+        if !self.system_proxy {
+            builder = builder.no_proxy();
+        }
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        for (domain, addrs) in &self.resolve {
+            builder = builder.resolve_to_addrs(domain, addrs);
+        }
+        for pem in &self.root_certificates {
+            builder = builder.add_root_certificate(Certificate::from_pem(pem)?);
+        }
+        builder.build()
+    }
+}
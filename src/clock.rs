@@ -0,0 +1,85 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+/// The source of the current time, used for release checks, cooldowns, retries and scheduling.
+/// Set with [`AoC::clock`](crate::AoC::clock).
+///
+/// The default is [`SystemClock`]. [`ManualClock`] allows tests to simulate unlocks without
+/// waiting for them.
+pub trait Clock: Send + Sync + 'static {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Block the current thread for `duration`
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration);
+    }
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when it is told to. Sleeping advances it instantly.
+///
+/// Share it with an [`AoC`](crate::AoC) through an [`Arc`] to keep control of it:
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use emergence::{AoC, ManualClock};
+/// let clock = Arc::new(ManualClock::new("2022-12-01T04:59:00Z".parse().unwrap()));
+/// let cache = std::env::temp_dir().join("emergence-clock");
+/// let aoc = AoC::with_path_and_token(2022, cache, "token".into())?.clock(clock.clone());
+/// assert!(aoc.read_or_fetch(1).is_err());
+/// clock.advance(std::time::Duration::from_secs(60));
+/// # Ok::<(), emergence::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl ManualClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use reqwest::header::{HeaderMap, SET_COOKIE};
+
+use crate::Fs;
+
+/// Cookies set by Advent of Code, persisted in the cache directory so they survive between runs
+///
+/// Only the names and values are kept, as every cookie comes from the same site. The `session`
+/// cookie is never stored, as it always comes from the configured token.
+pub(crate) struct CookieJar {
+    fs: Arc<dyn Fs>,
+    path: PathBuf,
+    cookies: Mutex<BTreeMap<String, String>>,
+}
+
+impl CookieJar {
+    /// Load the jar stored at `path`, which doesn't need to exist yet
+    pub(crate) fn load(fs: Arc<dyn Fs>, path: &Path) -> io::Result<Self> {
+        let cookies = fs
+            .read(path)?
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect();
+        Ok(Self {
+            fs,
+            path: path.to_owned(),
+            cookies: Mutex::new(cookies),
+        })
+    }
+
+    /// A copy of this jar, saved to `fs` from now on
+    pub(crate) fn with_fs(&self, fs: Arc<dyn Fs>) -> Self {
+        Self {
+            fs,
+            path: self.path.clone(),
+            cookies: Mutex::new(self.cookies.lock().unwrap().clone()),
+        }
+    }
+
+    /// The value of the `Cookie` header for a request made with `token`, sent as the cookie named
+    /// `token_cookie`
+    pub(crate) fn header(&self, token_cookie: &str, token: &str) -> String {
+        let cookies = self.cookies.lock().unwrap();
+        let mut header = format!("{token_cookie}={token}");
+        for (name, value) in cookies.iter() {
+            header.push_str("; ");
+            header.push_str(name);
+            header.push('=');
+            header.push_str(value);
+        }
+        header
+    }
+
+    /// Update the jar from the `Set-Cookie` headers of a response, saving it if it changed. The
+    /// token's cookie, `token_cookie`, is never updated
+    pub(crate) fn update(&self, headers: &HeaderMap, token_cookie: &str) -> io::Result<()> {
+        let mut cookies = self.cookies.lock().unwrap();
+        let mut changed = false;
+        for header in headers.get_all(SET_COOKIE) {
+            let Ok(header) = header.to_str() else {
+                continue;
+            };
+            let mut attrs = header.split(';').map(str::trim);
+            let Some((name, value)) = attrs.next().and_then(|c| c.split_once('=')) else {
+                continue;
+            };
+            if name == token_cookie {
+                continue;
+            }
+            let expired = attrs.any(|attr| {
+                attr.eq_ignore_ascii_case("max-age=0")
+                    || attr
+                        .to_ascii_lowercase()
+                        .starts_with("expires=thu, 01 jan 1970")
+            });
+            changed |= if expired || value.is_empty() {
+                cookies.remove(name).is_some()
+            } else {
+                cookies.insert(name.to_owned(), value.to_owned()).as_deref() != Some(value)
+            };
+        }
+
+        if changed {
+            let text: String = cookies
+                .iter()
+                .map(|(name, value)| format!("{name}={value}\n"))
+                .collect();
+            self.fs.write(&self.path, &text)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryFs;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn persists() {
+        let fs: Arc<dyn Fs> = Arc::new(MemoryFs::new());
+        let path = Path::new("cookies");
+        let jar = CookieJar::load(fs.clone(), path).unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.append(SET_COOKIE, HeaderValue::from_static("a=1; Path=/; Secure"));
+        headers.append(SET_COOKIE, HeaderValue::from_static("session=evil"));
+        headers.append(SET_COOKIE, HeaderValue::from_static("b=2"));
+        jar.update(&headers, "session").unwrap();
+        assert_eq!(jar.header("session", "TOKEN"), "session=TOKEN; a=1; b=2");
+
+        let mut headers = HeaderMap::new();
+        headers.append(SET_COOKIE, HeaderValue::from_static("a=; Max-Age=0"));
+        jar.update(&headers, "session").unwrap();
+
+        let jar = CookieJar::load(fs, path).unwrap();
+        assert_eq!(jar.header("session", "TOKEN"), "session=TOKEN; b=2");
+    }
+}
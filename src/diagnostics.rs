@@ -0,0 +1,140 @@
+use std::{collections::VecDeque, fmt, path::PathBuf, sync::Mutex};
+
+/// Where the token in use came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenSource {
+    /// Passed to [`AoC::with_path_and_token`](crate::AoC::with_path_and_token) or similar
+    Given,
+    /// The `$TOKEN` environment variable
+    Env,
+    /// `TOKEN` in a `.env` file found in the current directory or one of its parents
+    #[cfg(feature = "dotenv")]
+    DotEnv(PathBuf),
+    /// The file named by `$TOKEN_FILE`
+    EnvFile(PathBuf),
+    /// A `tokenfile` found in the current directory or one of its parents
+    Tokenfile(PathBuf),
+}
+
+impl fmt::Display for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Given => write!(f, "given explicitly"),
+            Self::Env => write!(f, "$TOKEN"),
+            #[cfg(feature = "dotenv")]
+            Self::DotEnv(path) => write!(f, "TOKEN in {}", path.display()),
+            Self::EnvFile(path) => write!(f, "$TOKEN_FILE ({})", path.display()),
+            Self::Tokenfile(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// A request made to Advent of Code, as recorded for [`Diagnostics`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestRecord {
+    pub method: String,
+    pub url: String,
+    /// The response status, or the error if there was no response
+    pub outcome: Result<u16, String>,
+}
+
+/// The most recent requests made by an [`AoC`](crate::AoC) and its clones
+#[derive(Debug, Default)]
+pub(crate) struct History(Mutex<VecDeque<RequestRecord>>);
+
+impl History {
+    const LEN: usize = 10;
+
+    pub(crate) fn record(&self, record: RequestRecord) {
+        let mut history = self.0.lock().unwrap();
+        if history.len() == Self::LEN {
+            history.pop_front();
+        }
+        history.push_back(record);
+    }
+
+    pub(crate) fn get(&self) -> Vec<RequestRecord> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Information for bug reports, as returned by [`AoC::diagnostics`](crate::AoC::diagnostics)
+///
+/// Nothing secret is included, so the [`Display`](fmt::Display) output can be pasted into an
+/// issue as-is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostics {
+    /// The version of emergence
+    pub version: String,
+    pub year: usize,
+    pub cache_path: PathBuf,
+    /// Whether the cache directory exists on disk. It won't for in-memory caches
+    pub cache_exists: bool,
+    /// The Unix permission bits of the cache directory, as given by [`Fs::mode`](crate::Fs::mode)
+    pub cache_mode: Option<u32>,
+    pub token_source: TokenSource,
+    /// A hash of the token in use, to tell whether two reports used the same token without
+    /// revealing it
+    pub token_hash: u64,
+    /// The index of the token in use, see [`AoC::active_token`](crate::AoC::active_token)
+    pub active_token: usize,
+    /// The last few requests made, oldest first
+    pub recent_requests: Vec<RequestRecord>,
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "emergence {}", self.version)?;
+        writeln!(f, "year: {}", self.year)?;
+        writeln!(
+            f,
+            "cache: {} ({})",
+            self.cache_path.display(),
+            match self.cache_mode {
+                None => "not on disk".to_owned(),
+                Some(mode) if mode & 0o200 == 0 => format!("mode {mode:o}, read-only"),
+                Some(mode) => format!("mode {mode:o}"),
+            }
+        )?;
+        writeln!(
+            f,
+            "token: {} (hash {:016x}, token #{})",
+            self.token_source, self.token_hash, self.active_token
+        )?;
+        writeln!(f, "recent requests:")?;
+        if self.recent_requests.is_empty() {
+            writeln!(f, "  none")?;
+        }
+        for req in &self.recent_requests {
+            match &req.outcome {
+                Ok(status) => writeln!(f, "  {} {} -> {status}", req.method, req.url)?,
+                Err(e) => writeln!(f, "  {} {} -> error: {e}", req.method, req.url)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history() {
+        let history = History::default();
+        for i in 0..15 {
+            history.record(RequestRecord {
+                method: "GET".into(),
+                url: i.to_string(),
+                outcome: Ok(200),
+            });
+        }
+        let recent = history.get();
+        assert_eq!(recent.len(), History::LEN);
+        assert_eq!(recent[0].url, "5");
+        assert_eq!(recent[9].url, "14");
+    }
+}
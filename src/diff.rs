@@ -0,0 +1,178 @@
+use std::fmt;
+
+/// A single line of an [`InputDiff`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    /// A line present in both inputs
+    Same(String),
+    /// A line only present in the cached input
+    Removed(String),
+    /// A line only present in the other input
+    Added(String),
+}
+
+/// A line-by-line diff between a cached input and another version of it, as returned by
+/// [`AoC::diff_input`](crate::AoC::diff_input)
+///
+/// The [`Display`](fmt::Display) implementation renders it like `diff`, prefixing lines with
+/// ` `, `-` or `+`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputDiff {
+    pub lines: Vec<DiffLine>,
+}
+
+impl InputDiff {
+    /// Compute the diff between two texts, using the longest common subsequence of their lines
+    pub(crate) fn new(old: &str, new: &str) -> Self {
+        let old: Vec<_> = old.lines().collect();
+        let new: Vec<_> = new.lines().collect();
+
+        // Only the differing middle section needs the quadratic-time algorithm
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let (a, b) = (
+            &old[prefix..old.len() - suffix],
+            &new[prefix..new.len() - suffix],
+        );
+
+        let mut lines: Vec<_> = old[..prefix]
+            .iter()
+            .map(|l| DiffLine::Same(l.to_string()))
+            .collect();
+        diff(a, b, &mut lines);
+        lines.extend(
+            old[old.len() - suffix..]
+                .iter()
+                .map(|l| DiffLine::Same(l.to_string())),
+        );
+
+        Self { lines }
+    }
+
+    /// Whether the two inputs are identical, line for line
+    pub fn is_empty(&self) -> bool {
+        self.lines.iter().all(|l| matches!(l, DiffLine::Same(_)))
+    }
+
+    /// The number of lines only present in the cached input
+    pub fn removed(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|l| matches!(l, DiffLine::Removed(_)))
+            .count()
+    }
+
+    /// The number of lines only present in the other input
+    pub fn added(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|l| matches!(l, DiffLine::Added(_)))
+            .count()
+    }
+}
+
+/// Append the diff from `a` to `b` to `lines`, using Hirschberg's algorithm to find the longest
+/// common subsequence of their lines in linear space
+fn diff(a: &[&str], b: &[&str], lines: &mut Vec<DiffLine>) {
+    let added = |b: &[&str]| {
+        b.iter()
+            .map(|l| DiffLine::Added(l.to_string()))
+            .collect::<Vec<_>>()
+    };
+    match a {
+        [] => lines.extend(added(b)),
+        _ if b.is_empty() => lines.extend(a.iter().map(|l| DiffLine::Removed(l.to_string()))),
+        [line] => match b.iter().position(|l| l == line) {
+            Some(j) => {
+                lines.extend(added(&b[..j]));
+                lines.push(DiffLine::Same(line.to_string()));
+                lines.extend(added(&b[j + 1..]));
+            }
+            None => {
+                lines.push(DiffLine::Removed(line.to_string()));
+                lines.extend(added(b));
+            }
+        },
+        _ => {
+            // Split `b` where the LCS of the two halves of `a` with its two parts is longest
+            let mid = a.len() / 2;
+            let left = lcs_lengths(a[..mid].iter(), b.iter());
+            let right = lcs_lengths(a[mid..].iter().rev(), b.iter().rev());
+            // The earliest split, so that removed lines come before added ones
+            let split = (0..=b.len())
+                .rev()
+                .max_by_key(|&j| left[j] + right[b.len() - j])
+                .unwrap();
+            diff(&a[..mid], &b[..split], lines);
+            diff(&a[mid..], &b[split..], lines);
+        }
+    }
+}
+
+/// The length of the longest common subsequence of `a` with each prefix of `b`, by length
+fn lcs_lengths<'a, A, B>(a: A, b: B) -> Vec<usize>
+where
+    A: Iterator<Item = &'a &'a str>,
+    B: Iterator<Item = &'a &'a str> + Clone,
+{
+    let mut row = vec![0; b.clone().count() + 1];
+    for x in a {
+        // `row[j - 1]` from the previous row
+        let mut diagonal = 0;
+        for (j, y) in b.clone().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if x == y {
+                diagonal + 1
+            } else {
+                above.max(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row
+}
+
+impl fmt::Display for InputDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            match line {
+                DiffLine::Same(l) => writeln!(f, " {l}")?,
+                DiffLine::Removed(l) => writeln!(f, "-{l}")?,
+                DiffLine::Added(l) => writeln!(f, "+{l}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff() {
+        let diff = InputDiff::new("a\nb\nc\nd\n", "a\nx\nc\nd\ne\n");
+        assert_eq!(diff.to_string(), " a\n-b\n+x\n c\n d\n+e\n");
+        assert_eq!((diff.added(), diff.removed()), (2, 1));
+        assert!(!diff.is_empty());
+        assert!(InputDiff::new("a\nb\n", "a\nb").is_empty());
+
+        let diff = InputDiff::new("x\na\nb\nc\ny\n", "a\nz\nc\nb\n");
+        assert_eq!(diff.to_string(), "-x\n a\n-b\n+z\n c\n-y\n+b\n");
+    }
+
+    #[test]
+    fn large() {
+        // Inputs that differ almost everywhere still only take memory proportional to their length
+        let old: String = (0..3_000).map(|i| format!("{i}\n")).collect();
+        let new: String = (0..3_000).map(|i| format!("{}\n", i * 3)).collect();
+        let diff = InputDiff::new(&old, &new);
+        assert_eq!(diff.removed(), 3_000 - 1_000);
+        assert_eq!(diff.added(), 3_000 - 1_000);
+    }
+}
@@ -0,0 +1,50 @@
+//! Just enough of the `.env` format to read a token from one.
+//!
+//! Unlike most `.env` loaders, nothing is put into the process's environment, which is unsafe to
+//! modify once other threads may be reading it.
+
+/// The value of `key` in the `.env` file `text`, if it is set
+///
+/// Values may be quoted with `"` or `'`, and lines may start with `export`. Comments and blank
+/// lines are skipped. Escapes and variable expansion are not supported.
+pub(crate) fn var(text: &str, key: &str) -> Option<String> {
+    // Later definitions win, so search from the end
+    text.lines()
+        .rev()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (name, value) = line.split_once('=')?;
+            (name.trim() == key).then(|| unquote(value.trim()).to_owned())
+        })
+        .next()
+}
+
+/// `value` without surrounding quotes, if it has them
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|value| value.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn var() {
+        let env = "# AoC\nTOKEN=first\nexport TOKEN = \"second\"\nOTHER='x'\n";
+        assert_eq!(super::var(env, "TOKEN").as_deref(), Some("second"));
+        assert_eq!(super::var(env, "OTHER").as_deref(), Some("x"));
+        assert_eq!(super::var(env, "MISSING"), None);
+        assert_eq!(super::var("#TOKEN=commented", "TOKEN"), None);
+        assert_eq!(unquote("\"unterminated"), "\"unterminated");
+    }
+}
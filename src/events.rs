@@ -0,0 +1,71 @@
+use crate::html;
+
+/// An Advent of Code event, as returned by [`AoC::events`](crate::AoC::events)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Event {
+    pub year: usize,
+    /// The stars the user has collected in this event
+    pub stars: usize,
+}
+
+/// Parse the events from the HTML of `https://adventofcode.com/events`, oldest first
+pub(crate) fn parse(page: &str) -> Vec<Event> {
+    let mut events: Vec<_> = page
+        .split("<div class=\"eventlist-event\"")
+        .skip(1)
+        .filter_map(parse_event)
+        .collect();
+    events.sort_by_key(|e| e.year);
+    events
+}
+
+/// Parse a single event, e.g. `><a href="/2020">[2020]</a> <span class="star-count">34*</span>`.
+/// Events with no stars have no star count
+fn parse_event(event: &str) -> Option<Event> {
+    let event = &event[..event.find("</div>").unwrap_or(event.len())];
+    let year = html::attr(event, "href")?.trim_matches('/').parse().ok()?;
+    let stars = match html::element(event, "<span class=\"star-count\"", "</span>") {
+        Some(count) => html::text(count)
+            .trim()
+            .trim_end_matches('*')
+            .parse()
+            .ok()?,
+        None => 0,
+    };
+    Some(Event { year, stars })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<main>
+<article><p>Here are all of the past events:</p>
+<div class="eventlist-event"><a href="/2016">[2016]</a> <span class="star-count">50*</span></div>
+<div class="eventlist-event"><a href="/2015">[2015]</a></div>
+<div class="eventlist-event"><a href="/2017">[2017]</a> <span class="star-count">7*</span></div>
+</article>
+</main>"#;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            super::parse(PAGE),
+            [
+                Event {
+                    year: 2015,
+                    stars: 0,
+                },
+                Event {
+                    year: 2016,
+                    stars: 50,
+                },
+                Event {
+                    year: 2017,
+                    stars: 7,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use std::{collections::BTreeMap, fmt};
+
+/// A summary of an input that can be shared without sharing the input itself, as returned by
+/// [`AoC::input_fingerprint`](crate::AoC::input_fingerprint)
+///
+/// Two people with the same input and the same salt get the same fingerprint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fingerprint {
+    /// A hash of the salt and the input. This is stable between versions and platforms
+    pub hash: u64,
+    pub lines: usize,
+    /// How many lines there are of each length, in bytes
+    pub line_lengths: BTreeMap<usize, usize>,
+}
+
+impl Fingerprint {
+    pub(crate) fn new(input: &str, salt: &str) -> Self {
+        let mut line_lengths = BTreeMap::new();
+        for line in input.lines() {
+            *line_lengths.entry(line.len()).or_default() += 1;
+        }
+        Self {
+            hash: fnv1a([salt.as_bytes(), &[0], input.as_bytes()]),
+            lines: input.lines().count(),
+            line_lengths,
+        }
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    /// e.g. `3f1a...: 200 lines (3 chars x12, 4 chars x188)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}: {} lines (", self.hash, self.lines)?;
+        for (i, (len, count)) in self.line_lengths.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{len} chars x{count}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// 64-bit FNV-1a, chosen as it is tiny and, unlike std's hashers, guaranteed to be stable
+pub(crate) fn fnv1a<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in parts.into_iter().flatten() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint() {
+        let a = Fingerprint::new("123\n45\n678\n", "friends");
+        assert_eq!(a.lines, 3);
+        assert_eq!(a.line_lengths, BTreeMap::from([(2, 1), (3, 2)]));
+        assert_eq!(a, Fingerprint::new("123\n45\n678\n", "friends"));
+        assert_ne!(a.hash, Fingerprint::new("123\n45\n678\n", "enemies").hash);
+        assert_ne!(a.hash, Fingerprint::new("123\n46\n678\n", "friends").hash);
+        assert!(
+            a.to_string()
+                .ends_with(": 3 lines (2 chars x1, 3 chars x2)")
+        );
+
+        assert_eq!(fnv1a([b"".as_slice()]), 0xcbf29ce484222325);
+        assert_eq!(fnv1a([b"a".as_slice()]), 0xaf63dc4c8601ec8c);
+    }
+}
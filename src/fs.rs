@@ -0,0 +1,198 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// The filesystem the cache is stored in. Set with [`AoC::with_fs`](crate::AoC::with_fs).
+///
+/// The default is [`OsFs`]. [`MemoryFs`] keeps everything in memory, so tests don't touch the
+/// disk at all, and [`PackFs`](crate::PackFs) keeps a whole cache in a single file. Snapshots
+/// and git sync always use the real filesystem.
+pub trait Fs: Send + Sync + 'static {
+    /// Read the file at `path`, or `None` if it does not exist
+    fn read(&self, path: &Path) -> io::Result<Option<String>>;
+
+    /// Write `contents` to the file at `path`, replacing it if it exists
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()>;
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Make sure the file at `path`, and its entry in its directory, have reached the disk.
+    /// Filesystems that aren't backed by a disk don't need to do anything
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        let _ = path;
+        Ok(())
+    }
+
+    /// Set the Unix permission bits of the file or directory at `path`, as used by
+    /// [`AoC::cache_permissions`](crate::AoC::cache_permissions). Filesystems without
+    /// permissions don't need to do anything
+    fn set_mode(&self, path: &Path, mode: u32) -> io::Result<()> {
+        let _ = (path, mode);
+        Ok(())
+    }
+
+    /// The Unix permission bits of the file or directory at `path`, or `None` if it doesn't
+    /// exist. Filesystems without permissions don't need to implement this, and always give `None`
+    fn mode(&self, path: &Path) -> io::Result<Option<u32>> {
+        let _ = path;
+        Ok(None)
+    }
+}
+
+impl<F: Fs + ?Sized> Fs for Arc<F> {
+    fn read(&self, path: &Path) -> io::Result<Option<String>> {
+        (**self).read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        (**self).write(path, contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        (**self).create_dir_all(path)
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        (**self).sync(path)
+    }
+
+    fn set_mode(&self, path: &Path, mode: u32) -> io::Result<()> {
+        (**self).set_mode(path, mode)
+    }
+
+    fn mode(&self, path: &Path) -> io::Result<Option<u32>> {
+        (**self).mode(path)
+    }
+}
+
+/// The real filesystem
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsFs;
+
+impl Fs for OsFs {
+    fn read(&self, path: &Path) -> io::Result<Option<String>> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(Some(text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        std::fs::write(path, contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        std::fs::File::open(path)?.sync_all()?;
+        // Directories can't be opened to sync them outside unix
+        #[cfg(unix)]
+        if let Some(dir) = path.parent() {
+            std::fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn set_mode(&self, path: &Path, mode: u32) -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+    }
+
+    /// Outside unix, only the read-only flag is available, so the mode is `0o444` or `0o666`
+    fn mode(&self, path: &Path) -> io::Result<Option<u32>> {
+        let metadata = match std::fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        #[cfg(unix)]
+        let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o7777;
+        #[cfg(not(unix))]
+        let mode = if metadata.permissions().readonly() {
+            0o444
+        } else {
+            0o666
+        };
+        Ok(Some(mode))
+    }
+}
+
+/// Another filesystem, with permissions applied to everything written to it, see
+/// [`AoC::cache_permissions`](crate::AoC::cache_permissions)
+#[cfg(unix)]
+pub(crate) struct WithModes {
+    pub(crate) inner: Arc<dyn Fs>,
+    pub(crate) dir_mode: u32,
+    pub(crate) file_mode: u32,
+}
+
+#[cfg(unix)]
+impl Fs for WithModes {
+    fn read(&self, path: &Path) -> io::Result<Option<String>> {
+        self.inner.read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.inner.write(path, contents)?;
+        self.inner.set_mode(path, self.file_mode)?;
+        // The directory may have been created before the permissions were set
+        match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => self.inner.set_mode(dir, self.dir_mode),
+            _ => Ok(()),
+        }
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.inner.create_dir_all(path)?;
+        self.inner.set_mode(path, self.dir_mode)
+    }
+
+    fn sync(&self, path: &Path) -> io::Result<()> {
+        self.inner.sync(path)
+    }
+
+    fn set_mode(&self, path: &Path, mode: u32) -> io::Result<()> {
+        self.inner.set_mode(path, mode)
+    }
+
+    fn mode(&self, path: &Path) -> io::Result<Option<u32>> {
+        self.inner.mode(path)
+    }
+}
+
+/// A filesystem held entirely in memory, which starts empty. Directories always exist.
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    files: Mutex<HashMap<PathBuf, String>>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Fs for MemoryFs {
+    fn read(&self, path: &Path) -> io::Result<Option<String>> {
+        Ok(self.files.lock().unwrap().get(path).cloned())
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.to_owned(), contents.to_owned());
+        Ok(())
+    }
+
+    fn create_dir_all(&self, _: &Path) -> io::Result<()> {
+        Ok(())
+    }
+}
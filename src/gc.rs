@@ -0,0 +1,202 @@
+//! Garbage collection of old inputs and snapshots, so long-lived shared caches don't grow
+//! without bound.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    cache::{NOTES_SUFFIX, SNAPSHOTS},
+    latest_year,
+};
+
+/// What [`AoC::gc`](crate::AoC::gc) is allowed to remove
+///
+/// Inputs and snapshots are the only things removed; the manifest, cookies and git metadata are
+/// always kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcPolicy {
+    /// Remove the least recently modified entries until the cache is at most this many bytes
+    pub max_size: Option<u64>,
+    /// Remove entries that were last modified longer ago than this
+    pub max_age: Option<Duration>,
+    /// Never remove inputs for the most recent event. This is the default
+    pub keep_current_year: bool,
+}
+
+impl Default for GcPolicy {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            max_age: None,
+            keep_current_year: true,
+        }
+    }
+}
+
+/// What [`AoC::gc`](crate::AoC::gc) removed
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GcReport {
+    /// Inputs and snapshots that were removed, least recently modified first
+    pub removed: Vec<PathBuf>,
+    /// The total size of everything removed, in bytes
+    pub freed: u64,
+}
+
+/// An input or snapshot in the cache
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    modified: DateTime<Utc>,
+    keep: bool,
+}
+
+/// Remove entries from the cache at `root` according to `policy`
+pub(crate) fn gc(root: &Path, policy: &GcPolicy, now: DateTime<Utc>) -> io::Result<GcReport> {
+    let mut entries = entries(root, policy, now)?;
+    entries.sort_by_key(|e| e.modified);
+    let mut size: u64 = entries.iter().map(|e| e.size).sum();
+
+    let mut report = GcReport::default();
+    for entry in entries.into_iter().filter(|e| !e.keep) {
+        let expired = policy
+            .max_age
+            .is_some_and(|max_age| (now - entry.modified).to_std().unwrap_or_default() > max_age);
+        let too_big = policy.max_size.is_some_and(|max_size| size > max_size);
+        if !expired && !too_big {
+            continue;
+        }
+
+        if entry.path.is_dir() {
+            std::fs::remove_dir_all(&entry.path)?;
+        } else {
+            std::fs::remove_file(&entry.path)?;
+        }
+        size -= entry.size;
+        report.freed += entry.size;
+        report.removed.push(entry.path);
+    }
+    Ok(report)
+}
+
+/// Every input and snapshot in the cache at `root`
+fn entries(root: &Path, policy: &GcPolicy, now: DateTime<Utc>) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for dir in std::fs::read_dir(root)? {
+        let dir = dir?;
+        let name = dir.file_name();
+        let keep = if name == SNAPSHOTS {
+            false
+        } else if let Some(year) = name.to_str().and_then(|name| name.parse::<usize>().ok()) {
+            policy.keep_current_year && year == latest_year(now)
+        } else {
+            continue;
+        };
+        if !dir.file_type()?.is_dir() {
+            continue;
+        }
+
+        for entry in std::fs::read_dir(dir.path())? {
+            let entry = entry?;
+            // Notes are written by hand, so can't be fetched again like everything else
+            if entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.ends_with(NOTES_SUFFIX))
+            {
+                continue;
+            }
+            entries.push(Entry {
+                path: entry.path(),
+                size: size(&entry.path())?,
+                modified: entry.metadata()?.modified()?.into(),
+                keep,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// The total size of the file or directory at `path`
+fn size(path: &Path) -> io::Result<u64> {
+    let metadata = std::fs::metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    std::fs::read_dir(path)?
+        .map(|entry| size(&entry?.path()))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, time::SystemTime};
+
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn policies() {
+        let dir = TempDir::new("emergence").unwrap();
+        let root = dir.path();
+        let now: DateTime<Utc> = "2023-12-10T00:00:00Z".parse().unwrap();
+        let day = Duration::from_secs(24 * 60 * 60);
+        let days_ago = |days: u32| SystemTime::from(now) - day * days;
+
+        // Each entry is 10 bytes, last modified the given number of days ago
+        let add = |path: &str, days: u32| {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, "0123456789").unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(days_ago(days))
+                .unwrap();
+        };
+        add("2021/day01.txt", 700);
+        add("2021/day01.notes.md", 700);
+        add("2022/day01.txt", 300);
+        add("2022/day02.txt", 200);
+        add("2023/day01.txt", 400);
+        add("snapshots/a/2022/day01.txt", 100);
+        File::open(root.join("snapshots/a"))
+            .unwrap()
+            .set_modified(days_ago(100))
+            .unwrap();
+        std::fs::write(root.join("manifest"), "version=1\n").unwrap();
+
+        let report = gc(root, &GcPolicy::default(), now).unwrap();
+        assert!(report.removed.is_empty());
+
+        let policy = GcPolicy {
+            max_age: Some(day * 365),
+            ..Default::default()
+        };
+        let report = gc(root, &policy, now).unwrap();
+        assert_eq!(report.removed, [root.join("2021/day01.txt")]);
+        assert_eq!(report.freed, 10);
+        assert!(root.join("2021/day01.notes.md").exists());
+
+        // 2023 is kept despite being older, as it is the current year
+        let policy = GcPolicy {
+            max_size: Some(25),
+            ..Default::default()
+        };
+        let report = gc(root, &policy, now).unwrap();
+        assert_eq!(
+            report.removed,
+            [root.join("2022/day01.txt"), root.join("2022/day02.txt")]
+        );
+        assert!(root.join("2023/day01.txt").exists());
+        assert!(root.join("snapshots/a").exists());
+        assert!(root.join("manifest").exists());
+    }
+}
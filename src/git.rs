@@ -0,0 +1,84 @@
+//! Synchronising the cache between machines by making it a git repository.
+
+use std::{path::Path, process::Command};
+
+use crate::{Error, cache};
+
+/// Run `git` in `root` with the given arguments, returning its stdout
+fn git(root: &Path, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::Git(format!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Make the cache at `root` a git repository, if it isn't one already
+fn init(root: &Path) -> Result<(), Error> {
+    if root.join(".git").exists() {
+        return Ok(());
+    }
+    git(root, &["init", "--quiet"])?;
+    std::fs::write(
+        root.join(".gitignore"),
+        format!("/{}/\n/cookies\n", cache::SNAPSHOTS),
+    )?;
+    commit(root, Path::new("."), "Initialise emergence cache")
+}
+
+/// Commit any changes to `path` (relative to `root`)
+pub(crate) fn commit(root: &Path, path: &Path, message: &str) -> Result<(), Error> {
+    init(root)?;
+    let path = path.to_string_lossy();
+    git(root, &["add", "--", &path])?;
+    // `git diff --cached --quiet` fails exactly when there is something to commit
+    if git(root, &["diff", "--cached", "--quiet"]).is_err() {
+        git(root, &["commit", "--quiet", "-m", message])?;
+    }
+    Ok(())
+}
+
+/// Pull changes from and push changes to the cache repository's upstream, if it has one
+pub(crate) fn sync(root: &Path) -> Result<(), Error> {
+    init(root)?;
+    if git(root, &["remote"])?.trim().is_empty() {
+        return Ok(());
+    }
+    git(root, &["pull", "--quiet", "--rebase"])?;
+    git(root, &["push", "--quiet"])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn commits() {
+        let dir = TempDir::new("emergence").unwrap();
+        let root = dir.path();
+        git(root, &["init", "--quiet"]).unwrap();
+        git(root, &["config", "user.name", "emergence"]).unwrap();
+        git(root, &["config", "user.email", "emergence@localhost"]).unwrap();
+
+        std::fs::create_dir(root.join("2020")).unwrap();
+        std::fs::write(root.join("2020/day01.txt"), "hello").unwrap();
+        commit(root, Path::new("2020/day01.txt"), "day 1").unwrap();
+        // Committing again with no changes is not an error
+        commit(root, Path::new("2020/day01.txt"), "day 1").unwrap();
+
+        let log = git(root, &["log", "--format=%s"]).unwrap();
+        assert_eq!(log, "day 1\n");
+        sync(root).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+//! Minimal helpers for scraping text out of Advent of Code's HTML pages.
+//!
+//! AoC's markup is simple and stable enough that a full HTML parser isn't worth the dependency.
+
+/// Find the value of the first `name="..."` attribute in `tag`
+pub(crate) fn attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let needle = format!("{name}=\"");
+    let start = tag.find(&needle)? + needle.len();
+    let len = tag[start..].find('"')?;
+    Some(&tag[start..start + len])
+}
+
+/// Find the contents of the first element opened by a tag starting with `open`, e.g.
+/// `<pre class="calendar`, up to the matching `close` tag
+pub(crate) fn element<'a>(html: &'a str, open: &str, close: &str) -> Option<&'a str> {
+    let start = html.find(open)?;
+    let start = start + html[start..].find('>')? + 1;
+    let len = html[start..].find(close)?;
+    Some(&html[start..start + len])
+}
+
+/// Strip all tags from `html` and decode the entities AoC uses, leaving only the text
+pub(crate) fn text(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        match rest[start..].find('>') {
+            Some(end) => rest = &rest[start + end + 1..],
+            None => {
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    unescape(&out)
+}
+
+/// Extract a human-readable message from a response body, which may be either plain text or an
+/// HTML page. For HTML, this is the text of the page's main content, with whitespace collapsed
+pub(crate) fn message(body: &str) -> String {
+    const MAX_LEN: usize = 500;
+
+    let text = if body.trim_start().starts_with('<') {
+        let main = element(body, "<article", "</article>")
+            .or_else(|| element(body, "<main", "</main>"))
+            .or_else(|| element(body, "<body", "</body>"))
+            .unwrap_or(body);
+        text(main)
+    } else {
+        body.to_owned()
+    };
+
+    let mut message = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if message.len() > MAX_LEN {
+        let mut end = MAX_LEN;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message.truncate(end);
+        message.push('…');
+    }
+    message
+}
+
+/// Decode the handful of HTML entities that appear in AoC pages
+pub(crate) fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_tags() {
+        assert_eq!(
+            text(r#"<span class="a">&lt;*&gt;</span> <a href="/">x &amp; y</a>"#),
+            "<*> x & y"
+        );
+        assert_eq!(
+            attr(r#"<a aria-label="Day 1" href="/">"#, "href"),
+            Some("/")
+        );
+        assert_eq!(
+            element("<p><pre class=\"x\">hi</pre>", "<pre", "</pre>"),
+            Some("hi")
+        );
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(
+            message("Puzzle inputs differ by user.  Please log in to get your puzzle input.\n"),
+            "Puzzle inputs differ by user. Please log in to get your puzzle input."
+        );
+        assert_eq!(
+            message(
+                "<!DOCTYPE html>\n<html><head><title>Day 1</title></head><body><header>nav</header>\n<main>\n<article><p>You don't seem to be solving the right level.  Did you already complete it? <a href=\"/2020/day/1\">[Return to Day 1]</a></p></article>\n</main></body></html>"
+            ),
+            "You don't seem to be solving the right level. Did you already complete it? [Return to Day 1]"
+        );
+        assert_eq!(message(&"x".repeat(1000)).chars().count(), 501);
+    }
+}
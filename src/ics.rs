@@ -0,0 +1,108 @@
+//! iCalendar (RFC 5545) export of puzzle unlock times.
+
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, Utc};
+
+/// A puzzle in an [`unlock_schedule`]
+pub(crate) struct Unlock {
+    pub(crate) day: usize,
+    pub(crate) time: DateTime<FixedOffset>,
+    /// The URL of the puzzle's input
+    pub(crate) input_url: String,
+}
+
+/// Render an iCalendar file generated at `now` with an event for each of `unlocks` in `year` of
+/// the event called `name`, each with a display alarm `reminder` before it unlocks
+pub(crate) fn unlock_schedule(
+    name: &str,
+    year: usize,
+    unlocks: &[Unlock],
+    now: DateTime<Utc>,
+    reminder: Option<Duration>,
+) -> String {
+    let format = "%Y%m%dT%H%M%SZ";
+    let mut ics = String::new();
+    let mut line = |s: &str| {
+        ics.push_str(s);
+        ics.push_str("\r\n");
+    };
+
+    line("BEGIN:VCALENDAR");
+    line("VERSION:2.0");
+    line("PRODID:-//emergence//Unlock schedule//EN");
+    line(&format!("X-WR-CALNAME:{name} {year}"));
+    for Unlock {
+        day,
+        time,
+        input_url,
+    } in unlocks
+    {
+        line("BEGIN:VEVENT");
+        line(&format!("UID:aoc-{year}-{day}@emergence"));
+        line(&format!("DTSTAMP:{}", now.format(format)));
+        line(&format!("DTSTART:{}", time.naive_utc().format(format)));
+        line("DURATION:PT1H");
+        line(&format!("SUMMARY:{name} {year} day {day}"));
+        // Advent of Code's puzzle pages are their inputs' parents
+        line(&format!(
+            "URL:{}",
+            input_url.strip_suffix("/input").unwrap_or(input_url)
+        ));
+        if let Some(reminder) = reminder {
+            line("BEGIN:VALARM");
+            line("ACTION:DISPLAY");
+            line(&format!("TRIGGER:-PT{}S", reminder.as_secs()));
+            line(&format!("DESCRIPTION:{name} {year} day {day} unlocks soon"));
+            line("END:VALARM");
+        }
+        line("END:VEVENT");
+    }
+    line("END:VCALENDAR");
+    ics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule() {
+        let unlocks: Vec<_> = (1..=25)
+            .map(|day| Unlock {
+                day,
+                time: crate::unlock_time(2020, day as _).unwrap(),
+                input_url: format!("https://adventofcode.com/2020/day/{day}/input"),
+            })
+            .collect();
+        let now = "2020-11-30T12:00:00Z".parse().unwrap();
+        let ics = unlock_schedule(
+            "Advent of Code",
+            2020,
+            &unlocks,
+            now,
+            Some(Duration::from_secs(15 * 60)),
+        );
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 25);
+        assert_eq!(ics.matches("DTSTAMP:20201130T120000Z\r\n").count(), 25);
+        assert!(ics.contains("X-WR-CALNAME:Advent of Code 2020\r\n"));
+        assert!(ics.contains("SUMMARY:Advent of Code 2020 day 1\r\n"));
+        assert!(ics.contains("DTSTART:20201201T050000Z\r\n"));
+        assert!(ics.contains("DTSTART:20201225T050000Z\r\n"));
+        assert!(ics.contains("URL:https://adventofcode.com/2020/day/25\r\n"));
+        assert!(ics.contains("TRIGGER:-PT900S\r\n"));
+        assert!(!unlock_schedule("Advent of Code", 2020, &unlocks, now, None).contains("VALARM"));
+
+        let ics = unlock_schedule(
+            "Practice",
+            2020,
+            &unlocks,
+            now,
+            Some(Duration::from_secs(30)),
+        );
+        assert!(ics.contains("DESCRIPTION:Practice 2020 day 1 unlocks soon\r\n"));
+        assert!(ics.contains("TRIGGER:-PT30S\r\n"));
+    }
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use crate::html;
+
+/// A user's placing on a global leaderboard
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    /// How long after the puzzle unlocked the user finished
+    pub time: Duration,
+    pub user_id: u64,
+    /// The user's display name, or `None` if they are anonymous
+    pub name: Option<String>,
+}
+
+/// The global top 100 for a day, as returned by
+/// [`AoC::global_leaderboard`](crate::AoC::global_leaderboard)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DayLeaderboard {
+    pub year: usize,
+    pub day: usize,
+    /// The first hundred users to get both stars
+    pub both_stars: Vec<LeaderboardEntry>,
+    /// The first hundred users to get the first star
+    pub first_star: Vec<LeaderboardEntry>,
+}
+
+impl DayLeaderboard {
+    /// Parse a leaderboard from the HTML of `https://adventofcode.com/{year}/leaderboard/day/{day}`
+    pub(crate) fn parse(year: usize, day: usize, page: &str) -> Self {
+        let (both, first) = page
+            .split_once("leaderboard-daydesc-first")
+            .unwrap_or((page, ""));
+        Self {
+            year,
+            day,
+            both_stars: parse_entries(day, both),
+            first_star: parse_entries(day, first),
+        }
+    }
+}
+
+fn parse_entries(day: usize, html: &str) -> Vec<LeaderboardEntry> {
+    html.split("<div class=\"leaderboard-entry\"")
+        .skip(1)
+        .filter_map(|entry| parse_entry(day, entry))
+        .collect()
+}
+
+/// Parse a single entry, e.g.
+/// `<div class="leaderboard-entry" data-user-id="1"><span class="leaderboard-position">  1)</span>
+/// <span class="leaderboard-time">Dec 01  00:00:53</span> name</div>`
+fn parse_entry(day: usize, entry: &str) -> Option<LeaderboardEntry> {
+    let entry = &entry[..entry.find("</div>").unwrap_or(entry.len())];
+    let user_id = html::attr(entry, "data-user-id")?.parse().ok()?;
+    let rank = html::text(html::element(
+        entry,
+        "<span class=\"leaderboard-position\"",
+        "</span>",
+    )?)
+    .trim()
+    .trim_end_matches(')')
+    .parse()
+    .ok()?;
+    let time = parse_time(
+        day,
+        &html::text(html::element(
+            entry,
+            "<span class=\"leaderboard-time\"",
+            "</span>",
+        )?),
+    )?;
+
+    let name = if entry.contains("leaderboard-anon") {
+        None
+    } else {
+        // The name is whatever is left after the time, minus the supporter badge
+        let rest = &entry[entry.find("leaderboard-time")?..];
+        let rest = &rest[rest.find("</span>")? + "</span>".len()..];
+        let rest = match rest.split_once("class=\"supporter-badge\"") {
+            Some((before, _)) => before.rsplit_once("<a ").map_or(before, |(name, _)| name),
+            None => rest,
+        };
+        Some(html::text(rest).trim().to_owned())
+    };
+
+    Some(LeaderboardEntry {
+        rank,
+        time,
+        user_id,
+        name,
+    })
+}
+
+/// Parse a completion time like `Dec 01  00:00:53` into the time since `day` unlocked
+fn parse_time(day: usize, time: &str) -> Option<Duration> {
+    let mut parts = time.split_whitespace();
+    let _month = parts.next()?;
+    let date: u64 = parts.next()?.parse().ok()?;
+    let mut hms = parts.next()?.split(':').map(|n| n.parse::<u64>().ok());
+    let (h, m, s) = (hms.next()??, hms.next()??, hms.next()??);
+    let days = date.checked_sub(day as u64)?;
+    Some(Duration::from_secs(((days * 24 + h) * 60 + m) * 60 + s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<main>
+<p>First hundred users to get <span class="leaderboard-daydesc-both">both stars</span> on Day 1:</p>
+<div class="leaderboard-entry" data-user-id="1001"><span class="leaderboard-position">  1)</span> <span class="leaderboard-time">Dec 01  00:01:13</span>  <a href="https://github.com/alice" target="_blank"><span class="leaderboard-userphoto"><img src="/a.png" height="20"/></span>alice</a> <a href="/2020/support" class="supporter-badge" title="Advent of Code Supporter">(AoC++)</a></div>
+<div class="leaderboard-entry" data-user-id="1002"><span class="leaderboard-position">  2)</span> <span class="leaderboard-time">Dec 02  00:00:04</span>  <span class="leaderboard-anon">(anonymous user #1002)</span></div>
+<p>First hundred users to get the <span class="leaderboard-daydesc-first">first star</span> on Day 1:</p>
+<div class="leaderboard-entry" data-user-id="1003"><span class="leaderboard-position">  1)</span> <span class="leaderboard-time">Dec 01  00:00:31</span>  <span class="leaderboard-userphoto"></span>bob &amp; co</div>
+</main>"#;
+
+    #[test]
+    fn parse() {
+        let board = DayLeaderboard::parse(2020, 1, PAGE);
+        assert_eq!(
+            board.both_stars,
+            [
+                LeaderboardEntry {
+                    rank: 1,
+                    time: Duration::from_secs(73),
+                    user_id: 1001,
+                    name: Some("alice".to_owned()),
+                },
+                LeaderboardEntry {
+                    rank: 2,
+                    time: Duration::from_secs(24 * 60 * 60 + 4),
+                    user_id: 1002,
+                    name: None,
+                },
+            ]
+        );
+        assert_eq!(
+            board.first_star,
+            [LeaderboardEntry {
+                rank: 1,
+                time: Duration::from_secs(31),
+                user_id: 1003,
+                name: Some("bob & co".to_owned()),
+            }]
+        );
+    }
+}
@@ -19,19 +19,109 @@
 //! ```
 
 use std::{
-    io,
+    collections::HashMap,
+    fmt, io,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock, RwLock},
 };
 
 use tap::TapOptional;
 use thiserror::Error;
 
 #[cfg(not(miri))]
-use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+mod audit;
+mod cache;
+mod calendar;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(not(miri))]
+mod cassette;
+#[cfg(not(miri))]
+mod circuit;
+#[cfg(not(miri))]
+mod client;
+#[cfg(not(miri))]
+mod clock;
+#[cfg(not(miri))]
+mod cookies;
+mod diagnostics;
+mod diff;
+#[cfg(feature = "dotenv")]
+mod dotenv;
+mod events;
+mod fingerprint;
+mod fs;
+#[cfg(not(miri))]
+mod gc;
+mod git;
+mod html;
+#[cfg(not(miri))]
+mod ics;
+mod leaderboard;
+#[cfg(not(miri))]
+mod middleware;
+mod pack;
+pub mod parse;
+#[cfg(not(miri))]
+mod provider;
+mod readme;
+#[cfg(not(miri))]
+mod retry;
+pub mod scaffold;
+#[cfg(not(miri))]
+mod serve;
+mod stats;
+mod svg;
+#[cfg(all(feature = "test-util", not(miri)))]
+pub mod test_util;
+mod tokenfile;
+#[cfg(not(miri))]
+mod trace;
+mod validate;
+
+#[cfg(not(miri))]
+pub use audit::AuditLog;
+pub use calendar::{Calendar, Stars};
+#[cfg(not(miri))]
+pub use cassette::Cassette;
+#[cfg(not(miri))]
+pub use client::ConnectionOptions;
+#[cfg(not(miri))]
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use diagnostics::{Diagnostics, RequestRecord, TokenSource};
+pub use diff::{DiffLine, InputDiff};
+pub use events::Event;
+pub use fingerprint::Fingerprint;
+pub use fs::{Fs, MemoryFs, OsFs};
+#[cfg(not(miri))]
+pub use gc::{GcPolicy, GcReport};
+pub use leaderboard::{DayLeaderboard, LeaderboardEntry};
+#[cfg(not(miri))]
+pub use middleware::{Middleware, Next};
+pub use pack::PackFs;
+#[cfg(not(miri))]
+pub use provider::{AdventOfCode, LocalDirectory, PuzzleProvider, UrlTemplate};
+pub use readme::{README_END, README_START};
+#[cfg(not(miri))]
+pub use retry::RetryPolicy;
+pub use stats::{DayStats, Stats};
+pub use tokenfile::TokenSearch;
+#[cfg(not(miri))]
+pub use trace::Trace;
+pub use validate::InputProblem;
+
+#[cfg(not(miri))]
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+#[cfg(not(miri))]
+use circuit::CircuitBreaker;
+#[cfg(not(miri))]
+use cookies::CookieJar;
+use diagnostics::History;
 #[cfg(not(miri))]
 use reqwest::{
-    blocking::Client,
-    header::{COOKIE, USER_AGENT},
+    Method, StatusCode,
+    blocking::{Client, RequestBuilder, Response},
+    header::{COOKIE, HeaderMap, HeaderName, HeaderValue, LOCATION, USER_AGENT},
 };
 
 #[derive(Debug, Error)]
@@ -42,82 +132,743 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error("Refusing to fetch input for day {0}, as it has not yet been released")]
     NotYetReleased(usize),
+    #[error("Advent of Code started in 2015, there is no event in {0}")]
+    InvalidYear(usize),
+    #[error("The {0} Advent of Code has not started yet")]
+    EventNotStarted(usize),
     #[error("Advent of Code problems are 1-indexed, day 0 does not exist")]
     DayZero,
     #[error("Advent of Code stops after the 25th")]
     OutOfBounds,
+    #[error("Advent of Code responded with {status}{}", if message.is_empty() { String::new() } else { format!(": {message}") })]
+    Status {
+        status: reqwest::StatusCode,
+        /// The human-readable message from the response, if there was one
+        message: String,
+    },
+    #[error("The session token was not accepted by Advent of Code, it may have expired")]
+    Unauthorized,
+    #[error("The response was cut off after {received} of {expected} bytes")]
+    Incomplete { expected: u64, received: u64 },
+    #[error("There is no puzzle for day {day} of {year}")]
+    PuzzleNotFound { year: usize, day: usize },
+    #[error("Advent of Code is rate limiting requests, please back off before trying again")]
+    Throttled,
+    #[cfg(feature = "notify")]
+    #[error(transparent)]
+    Notification(#[from] notify_rust::error::Error),
+    #[error("The cache was written by a newer version of emergence (cache version {0})")]
+    UnsupportedCacheVersion(u32),
+    #[error("{0}")]
+    Git(String),
+    #[error("Not making requests for another {0:?}, after too many consecutive failures")]
+    CircuitOpen(std::time::Duration),
+    #[error("Dry run: would have requested {0}")]
+    DryRun(String),
+    #[error("The response for day {day} doesn't look like an input: {}", problems.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    InvalidInput {
+        day: usize,
+        problems: Vec<InputProblem>,
+    },
+    #[error("No response to {0} was recorded in the cassette")]
+    NotRecorded(String),
+    #[error("Refusing to overwrite the cached input for day {}: {0}", .0.day)]
+    InputChanged(InputChange),
+    #[error("{} has no `{README_START}` followed by `{README_END}` to put the progress table between", .0.display())]
+    MissingReadmeMarkers(PathBuf),
+}
+
+/// A summary of how a freshly fetched input differs from the cached copy.
+///
+/// Returned (inside [`Error::InputChanged`]) by [`AoC::refetch`] when it would otherwise overwrite
+/// a cached input with different contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputChange {
+    pub day: usize,
+    /// A 64-bit FNV-1a hash of the cached input, which is the same in every version of emergence
+    pub cached_hash: u64,
+    /// A hash of the fetched input, as for `cached_hash`
+    pub fetched_hash: u64,
+    pub cached_lines: usize,
+    pub fetched_lines: usize,
+    /// The number of line positions at which the two inputs differ
+    pub differing_lines: usize,
+}
+
+impl InputChange {
+    fn new(day: usize, cached: &str, fetched: &str) -> Self {
+        let hash = |text: &str| fingerprint::fnv1a([text.as_bytes()]);
+
+        let cached_lines = cached.lines().count();
+        let fetched_lines = fetched.lines().count();
+        let differing_lines = cached
+            .lines()
+            .zip(fetched.lines())
+            .filter(|(a, b)| a != b)
+            .count()
+            + cached_lines.abs_diff(fetched_lines);
+
+        Self {
+            day,
+            cached_hash: hash(cached),
+            fetched_hash: hash(fetched),
+            cached_lines,
+            fetched_lines,
+            differing_lines,
+        }
+    }
+}
+
+impl fmt::Display for InputChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cached input ({} lines, hash {:016x}) differs from fetched input ({} lines, hash {:016x}) on {} lines",
+            self.cached_lines,
+            self.cached_hash,
+            self.fetched_lines,
+            self.fetched_hash,
+            self.differing_lines
+        )
+    }
+}
+
+/// A freshly fetched input that has just been written to the cache, passed to
+/// [`AoC::on_fetch`] hooks
+#[derive(Debug, Clone, Copy)]
+pub struct FetchEvent<'a> {
+    pub year: usize,
+    pub day: usize,
+    /// Where the input was cached
+    pub path: &'a Path,
+    pub input: &'a str,
+}
+
+/// How much of a response has been downloaded, passed to [`AoC::on_progress`] hooks
+#[derive(Debug, Clone, Copy)]
+pub struct Progress<'a> {
+    pub url: &'a str,
+    /// Bytes of the body downloaded so far
+    pub downloaded: u64,
+    /// The size of the body from the `Content-Length` header, if it was sent
+    pub total: Option<u64>,
+}
+
+/// A page served from the cache by [`AoC::calendar_cached`], [`AoC::global_leaderboard_cached`]
+/// or [`AoC::stats_cached`], along with how fresh it is
+#[cfg(not(miri))]
+#[derive(Debug)]
+pub struct Cached<T> {
+    pub value: T,
+    /// When `value` was fetched from Advent of Code
+    pub fetched: DateTime<Utc>,
+    /// Whether `value` is older than the requested TTL, in which case it is being refreshed in the
+    /// background
+    pub stale: bool,
+    /// The background refresh, if `value` is stale
+    pub refresh: Option<std::thread::JoinHandle<Result<(), Error>>>,
+}
+
+#[cfg(not(miri))]
+impl<T> Cached<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Cached<U> {
+        Cached {
+            value: f(self.value),
+            fetched: self.fetched,
+            stale: self.stale,
+            refresh: self.refresh,
+        }
+    }
+}
+
+/// What [`AoC::fetch_missing`] did for each released day
+///
+/// With the `serde` feature it can be serialised, with errors as their messages, but not
+/// deserialised, as errors can't be.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FetchReport {
+    /// Days that were fetched and cached
+    pub downloaded: Vec<usize>,
+    /// Days that were already cached
+    pub skipped: Vec<usize>,
+    /// Days that could not be fetched, and why
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_failures"))]
+    pub failed: Vec<(usize, Error)>,
+}
+
+/// Serialise [`FetchReport::failed`] with each error as its message
+#[cfg(feature = "serde")]
+fn serialize_failures<S>(failed: &[(usize, Error)], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.collect_seq(failed.iter().map(|(day, e)| (day, e.to_string())))
+}
+
+/// The tokens an [`AoC`] can authenticate with, in order of preference
+struct Tokens {
+    list: Vec<String>,
+    /// Index into `list` of the token currently in use
+    active: usize,
+    /// Where the first token came from. Unless it was given explicitly, it can be read again
+    source: TokenSource,
+}
+
+impl Tokens {
+    fn active(&self) -> &str {
+        &self.list[self.active]
+    }
 }
 
 /// The AoC struct is the main entry point for this library.
 ///
 /// See [`AoC::new`] and [`AoC::read_or_fetch`] for usage
+#[derive(Clone)]
 pub struct AoC {
     path: PathBuf,
-    token: String,
+    /// Shared between clones, so that a reloaded or fallen back to token is seen by all of them
+    tokens: Arc<RwLock<Tokens>>,
     year: usize,
+    dry_run: bool,
+    durable: bool,
+    git_sync: bool,
 
+    /// Built on first use, so that the connection options can be changed first
+    #[cfg(not(miri))]
+    client: OnceLock<Client>,
+    #[cfg(not(miri))]
+    connection_options: ConnectionOptions,
+    #[cfg(not(miri))]
+    cookies: Arc<CookieJar>,
     #[cfg(not(miri))]
-    client: Client,
+    headers: HeaderMap,
+    #[cfg(not(miri))]
+    request_hook: Option<RequestHook>,
+    #[cfg(not(miri))]
+    progress_hook: Option<ProgressHook>,
+    #[cfg(not(miri))]
+    middleware: Vec<Arc<dyn Middleware>>,
+    #[cfg(not(miri))]
+    circuit: Option<Arc<CircuitBreaker>>,
+    #[cfg(not(miri))]
+    retry_policy: RetryPolicy,
+    #[cfg(not(miri))]
+    clock: Arc<dyn Clock>,
+    #[cfg(not(miri))]
+    provider: Arc<dyn PuzzleProvider>,
+    #[cfg(not(miri))]
+    team_cache: Option<String>,
+    #[cfg(not(miri))]
+    audit_log: Option<Arc<AuditLog>>,
+    /// A lock per day, held while fetching it
+    #[cfg(not(miri))]
+    in_flight: Arc<Mutex<HashMap<usize, Arc<Mutex<()>>>>>,
+    /// Transforms applied to inputs before they are returned, and the day they apply to (or
+    /// `None` for every day)
+    transforms: Vec<(Option<usize>, Transform)>,
+    fetch_hooks: Vec<FetchHook>,
+    /// Inputs leaked by [`AoC::read_or_fetch_static`]
+    leaked: Arc<Mutex<HashMap<usize, &'static str>>>,
+    history: Arc<History>,
+    fs: Arc<dyn Fs>,
 }
 
+/// A function applied to inputs before they are returned, see [`AoC::transform`]
+type Transform = Arc<dyn Fn(String) -> String + Send + Sync>;
+
+/// A function called after an input is fetched and cached, see [`AoC::on_fetch`]
+type FetchHook = Arc<dyn Fn(&FetchEvent<'_>) + Send + Sync>;
+
+/// A function applied to every outgoing request, see [`AoC::request_hook`]
+#[cfg(not(miri))]
+type RequestHook = Arc<dyn Fn(RequestBuilder) -> RequestBuilder + Send + Sync>;
+
+/// A function called as response bodies are downloaded, see [`AoC::on_progress`]
+#[cfg(not(miri))]
+type ProgressHook = Arc<dyn Fn(&Progress<'_>) + Send + Sync>;
+
 impl AoC {
     /// Constructs a new AoC instance at the specified path with the given token
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidYear`] if `year` is before the first Advent of Code in 2015, or
+    /// [`Error::EventNotStarted`] if it is too far in the future to ever have an event. Whether
+    /// the event has started yet is only checked when fetching, against the [clock](AoC::clock).
     pub fn with_path_and_token(
         year: usize,
         path: impl AsRef<Path>,
         token: String,
     ) -> Result<Self, Error> {
-        assert!(year < 3000, "Year must be less than 3000");
-        std::fs::create_dir_all(path.as_ref().join(year.to_string()))?;
+        Self::with_fs(year, path, token, OsFs)
+    }
+
+    /// Constructs a new AoC instance with its cache at the specified path in `fs`, with the given
+    /// token. See [`MemoryFs`] for a cache that never touches the disk
+    ///
+    /// # Errors
+    ///
+    /// As [`AoC::with_path_and_token`]
+    pub fn with_fs(
+        year: usize,
+        path: impl AsRef<Path>,
+        token: String,
+        fs: impl Fs,
+    ) -> Result<Self, Error> {
+        if year < 2015 {
+            return Err(Error::InvalidYear(year));
+        }
+        #[cfg(not(miri))]
+        if i32::try_from(year)
+            .ok()
+            .and_then(|year| unlock_time(year, 1))
+            .is_none()
+        {
+            return Err(Error::EventNotStarted(year));
+        }
+
+        let fs: Arc<dyn Fs> = Arc::new(fs);
+        fs.create_dir_all(&path.as_ref().join(year.to_string()))?;
+        cache::migrate(&*fs, path.as_ref())?;
         Ok(Self {
             path: path.as_ref().to_owned(),
             year,
-            token,
+            tokens: Arc::new(RwLock::new(Tokens {
+                list: vec![token],
+                active: 0,
+                source: TokenSource::Given,
+            })),
+            dry_run: false,
+            durable: false,
+            git_sync: false,
 
             #[cfg(not(miri))]
-            client: Client::new(),
+            client: OnceLock::new(),
+            #[cfg(not(miri))]
+            connection_options: ConnectionOptions::default(),
+            #[cfg(not(miri))]
+            cookies: Arc::new(CookieJar::load(fs.clone(), &path.as_ref().join("cookies"))?),
+            #[cfg(not(miri))]
+            headers: HeaderMap::new(),
+            #[cfg(not(miri))]
+            request_hook: None,
+            #[cfg(not(miri))]
+            progress_hook: None,
+            #[cfg(not(miri))]
+            middleware: Vec::new(),
+            #[cfg(not(miri))]
+            circuit: None,
+            #[cfg(not(miri))]
+            retry_policy: RetryPolicy::none(),
+            #[cfg(not(miri))]
+            clock: Arc::new(SystemClock),
+            #[cfg(not(miri))]
+            provider: Arc::new(AdventOfCode),
+            #[cfg(not(miri))]
+            team_cache: None,
+            #[cfg(not(miri))]
+            audit_log: None,
+            #[cfg(not(miri))]
+            in_flight: Arc::default(),
+            transforms: Vec::new(),
+            fetch_hooks: Vec::new(),
+            leaked: Arc::default(),
+            history: Arc::default(),
+            fs,
         })
     }
 
-    /// Find a `./tokenfile` in the current directory, or search upwards recursively
-    fn find_tokenfile() -> Result<Option<PathBuf>, Error> {
-        let mut path = std::env::current_dir()?;
-        while {
-            let tokenpath = path.join("tokenfile");
-            if tokenpath.is_file() {
-                return Ok(Some(tokenpath));
-            }
-            path.pop()
-        } {}
-        Ok(None)
+    /// Enable or disable dry-run mode.
+    ///
+    /// In dry-run mode, anything that would make a request to Advent of Code instead returns
+    /// [`Error::DryRun`] with the URL it would have requested, which is also logged at the info
+    /// level. Reading from the cache is unaffected.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Enable or disable flushing cached inputs (and the directories containing them) to disk as
+    /// soon as they are written, so a crash straight after fetching can't lose them. This is
+    /// slower, so is disabled by default
+    pub fn durable(mut self, durable: bool) -> Self {
+        self.durable = durable;
+        self
+    }
+
+    /// Fetch inputs from `provider` rather than Advent of Code, for events run in the same way.
+    /// Use a separate cache directory for each provider, as inputs are cached by year and day
+    /// alone
+    ///
+    /// Only inputs come from the provider: the calendar, leaderboards, statistics and events are
+    /// always fetched from Advent of Code.
+    #[cfg(not(miri))]
+    pub fn provider(mut self, provider: impl PuzzleProvider + 'static) -> Self {
+        self.provider = Arc::new(provider);
+        self
+    }
+
+    /// Try to fetch inputs from a team cache server (see [`AoC::serve`]) at `url`, such as
+    /// `http://10.0.0.5:8080`, before falling back to the puzzle provider. This saves every
+    /// member of a team or CI matrix sharing an account from fetching each input separately
+    ///
+    /// The session token is never sent to the team cache. If it can't be reached, or doesn't have
+    /// a valid input, the input is fetched as usual.
+    #[cfg(not(miri))]
+    pub fn team_cache(mut self, url: impl Into<String>) -> Self {
+        self.team_cache = Some(url.into());
+        self
+    }
+
+    /// Restrict the permissions of the cache, as inputs and cookies belong to one account and
+    /// shouldn't be readable by other users on a shared machine. `dir_mode` and `file_mode` are
+    /// Unix permission bits, such as `0o700` and `0o600`
+    ///
+    /// Permissions are applied to files as they are written, and to the directories they are
+    /// written in. Files that are already cached are left as they are until they are next
+    /// written. Snapshots are not affected.
+    #[cfg(unix)]
+    pub fn cache_permissions(mut self, dir_mode: u32, file_mode: u32) -> Self {
+        self.fs = Arc::new(fs::WithModes {
+            inner: self.fs,
+            dir_mode,
+            file_mode,
+        });
+        #[cfg(not(miri))]
+        {
+            self.cookies = Arc::new(self.cookies.with_fs(self.fs.clone()));
+        }
+        self
+    }
+
+    /// Set the options used for the HTTP client's connections
+    #[cfg(not(miri))]
+    pub fn connection_options(mut self, options: ConnectionOptions) -> Self {
+        self.connection_options = options;
+        self.client = OnceLock::new();
+        self
+    }
+
+    /// Resolve `domain` to `addrs` instead of using DNS. The port of each address is ignored
+    ///
+    /// This is a shorthand for adding to [`ConnectionOptions::resolve`], so it must be called
+    /// after [`AoC::connection_options`].
+    #[cfg(not(miri))]
+    pub fn resolve(mut self, domain: &str, addrs: &[std::net::SocketAddr]) -> Self {
+        self.connection_options
+            .resolve
+            .push((domain.to_owned(), addrs.to_vec()));
+        self.client = OnceLock::new();
+        self
+    }
+
+    /// Trust the PEM-encoded certificate `pem` in addition to the default roots, for example when
+    /// behind a TLS-intercepting proxy. An invalid certificate makes every request fail
+    ///
+    /// This is a shorthand for adding to [`ConnectionOptions::root_certificates`], so it must be
+    /// called after [`AoC::connection_options`].
+    #[cfg(not(miri))]
+    pub fn root_certificate(mut self, pem: &[u8]) -> Self {
+        self.connection_options
+            .root_certificates
+            .push(pem.to_owned());
+        self.client = OnceLock::new();
+        self
+    }
+
+    /// Add a header to be sent with every request to Advent of Code.
+    ///
+    /// Headers added this way take precedence over the ones emergence sets itself.
+    #[cfg(not(miri))]
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Add tokens to fall back to, in order, if the current one is rejected by Advent of Code.
+    ///
+    /// Once a token has been rejected it is not tried again; see [`AoC::active_token`] for which
+    /// one is in use
+    #[cfg(not(miri))]
+    pub fn fallback_tokens<I>(self, tokens: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.tokens
+            .write()
+            .unwrap()
+            .list
+            .extend(tokens.into_iter().map(Into::into));
+        self
+    }
+
+    /// The index of the token currently in use: 0 for the token this instance was created with,
+    /// or 1 onwards for those given to [`AoC::fallback_tokens`]
+    pub fn active_token(&self) -> usize {
+        self.tokens.read().unwrap().active
+    }
+
+    /// Set a function to be applied to every request to Advent of Code just before it is sent,
+    /// after all headers have been set
+    #[cfg(not(miri))]
+    pub fn request_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(RequestBuilder) -> RequestBuilder + Send + Sync + 'static,
+    {
+        self.request_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set a function to be called each time part of a response body is downloaded, for showing
+    /// progress on large inputs
+    #[cfg(not(miri))]
+    pub fn on_progress<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Progress<'_>) + Send + Sync + 'static,
+    {
+        self.progress_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Enable or disable committing to git whenever an input is cached.
+    ///
+    /// The cache directory is made a git repository if it isn't one already. Use [`AoC::sync`]
+    /// to pull and push changes, after adding a remote to the repository yourself. If committing
+    /// fails, for example because git isn't installed or `user.email` isn't set, a warning is
+    /// logged and the input is still returned.
+    ///
+    /// # Inputs are not encrypted
+    ///
+    /// Inputs are committed, and pushed by [`AoC::sync`], in plain text, so anyone who can read
+    /// the remote can read every input. Advent of Code asks that inputs aren't shared, so only
+    /// ever push to a private repository.
+    pub fn git_sync(mut self, git_sync: bool) -> Self {
+        self.git_sync = git_sync;
+        self
+    }
+
+    /// Pull and push cached inputs to the cache repository's upstream (see [`AoC::git_sync`]).
+    /// Does nothing if the repository has no remote
+    pub fn sync(&self) -> Result<(), Error> {
+        git::sync(&self.path)
+    }
+
+    /// Add a middleware layer around every request to Advent of Code. Middleware added first is
+    /// outermost, and sees requests first and responses last
+    #[cfg(not(miri))]
+    pub fn middleware(mut self, middleware: impl Middleware) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Record every request made to Advent of Code in `log`, see [`AuditLog`]
+    #[cfg(not(miri))]
+    pub fn audit_log(mut self, log: AuditLog) -> Self {
+        self.audit_log = Some(Arc::new(log));
+        self
+    }
+
+    /// Stop making requests for `cooldown` after `threshold` consecutive failed requests, failing
+    /// fast with [`Error::CircuitOpen`] instead
+    ///
+    /// A request fails if it cannot be sent or Advent of Code returns a server error. After the
+    /// cooldown, a single further failure stops requests again. Clones of this instance share
+    /// the same circuit breaker.
+    #[cfg(not(miri))]
+    pub fn circuit_breaker(mut self, threshold: u32, cooldown: std::time::Duration) -> Self {
+        self.circuit = Some(Arc::new(CircuitBreaker::new(threshold, cooldown)));
+        self
+    }
+
+    /// Set how failed requests are retried. By default they are not retried at all
+    #[cfg(not(miri))]
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Set where the current time comes from. By default this is the system clock
+    ///
+    /// The clock is used for everything that depends on the time, including checking that the
+    /// event has started, so an event can be simulated before it has really started.
+    #[cfg(not(miri))]
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Add a transformation applied to every day's input before it is returned, such as trimming
+    /// it. The cached input is unaffected
+    ///
+    /// Transformations are applied in the order they are added, along with those added by
+    /// [`AoC::transform_day`].
+    pub fn transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(String) -> String + Send + Sync + 'static,
+    {
+        self.transforms.push((None, Arc::new(transform)));
+        self
+    }
+
+    /// Add a transformation applied to the specified day's input before it is returned
+    pub fn transform_day<F>(mut self, day: usize, transform: F) -> Self
+    where
+        F: Fn(String) -> String + Send + Sync + 'static,
+    {
+        self.transforms.push((Some(day), Arc::new(transform)));
+        self
+    }
+
+    /// Add a hook called after an input has been fetched and written to the cache, e.g. to open
+    /// it in an editor. Hooks are called in the order they are added
+    pub fn on_fetch<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&FetchEvent<'_>) + Send + Sync + 'static,
+    {
+        self.fetch_hooks.push(Arc::new(hook));
+        self
+    }
+
+    fn apply_transforms(&self, day: usize, input: String) -> String {
+        self.transforms
+            .iter()
+            .filter(|(only, _)| only.is_none_or(|only| only == day))
+            .fold(input, |input, (_, transform)| transform(input))
+    }
+
+    /// Find a tokenfile in the current directory, or search upwards recursively as far as
+    /// `search` allows
+    fn find_tokenfile(search: &TokenSearch) -> Result<Option<PathBuf>, Error> {
+        Ok(search.find(&search.names)?)
+    }
+
+    /// Read a token from the file at `path`, trimming any trailing whitespace. Logs a warning if
+    /// other users can read the file
+    fn read_tokenfile(path: impl AsRef<Path>) -> Option<String> {
+        let path = path.as_ref();
+        let token = std::fs::read_to_string(path)
+            .ok()
+            .tap_some_mut(|s| s.truncate(s.trim_end().len()))?;
+        if tokenfile::is_shared(path).unwrap_or(false) {
+            log::warn!(
+                "{} can be read by other users, who could use the session token in it. Run `chmod 600` on it to fix this",
+                path.display()
+            );
+        }
+        Some(token)
+    }
+
+    /// Write `token` to a tokenfile at `path`, replacing any token already there, so that it can
+    /// be found by [`AoC::with_path`]. The file is made readable only by the current user, as
+    /// the token gives full access to the Advent of Code account
+    pub fn create_tokenfile(path: impl AsRef<Path>, token: &str) -> Result<(), Error> {
+        Ok(tokenfile::create(path.as_ref(), token)?)
     }
 
-    /// Constructs a new AoC instance at the specified path, reading the token from `$TOKEN`
-    /// or `./tokenfile`
+    /// Constructs a new AoC instance at the specified path, reading the token from `$TOKEN`,
+    /// the file named by `$TOKEN_FILE`, or `./tokenfile`
+    ///
+    /// With the `dotenv` feature, `TOKEN` in a `./.env` file is checked after `$TOKEN`.
+    ///
+    /// `./tokenfile` is searched for in the current directory and its parents, up to the root of
+    /// the git repository or the home directory. See [`AoC::with_token_search`] to change this.
     pub fn with_path(year: usize, path: impl AsRef<Path>) -> Result<Self, Error> {
-        let tokenpath = Self::find_tokenfile()?;
-
-        let Some(token) = std::env::var("TOKEN").ok().or_else(|| {
-            tokenpath
-                .and_then(|tokenpath| std::fs::read_to_string(tokenpath).ok())
-                .tap_some_mut(|s| s.truncate(s.trim_end().len()))
-        }) else {
-            panic!("Could not read token from $TOKEN or find a ./tokenfile in this directory or any parent. Please set the token in one of these locations or use `AoC::with_path_and_token`");
+        Self::with_token_search(year, path, TokenSearch::default())
+    }
+
+    /// As [`AoC::with_path`], but searching for a tokenfile as described by `search`
+    pub fn with_token_search(
+        year: usize,
+        path: impl AsRef<Path>,
+        search: TokenSearch,
+    ) -> Result<Self, Error> {
+        let Some((token, source)) = Self::discover_token(&search)? else {
+            panic!("Could not read token from $TOKEN or $TOKEN_FILE, or find a ./tokenfile in this directory or any parent. Please set the token in one of these locations or use `AoC::with_path_and_token`");
+        };
+
+        let aoc = Self::with_path_and_token(year, path, token)?;
+        aoc.tokens.write().unwrap().source = source;
+        Ok(aoc)
+    }
+
+    /// Where [`AoC::new`] and [`AoC::with_path`] would read the token from, or `None` if they
+    /// wouldn't find one
+    pub fn token_source() -> Result<Option<TokenSource>, Error> {
+        Ok(Self::discover_token(&TokenSearch::default())?.map(|(_, source)| source))
+    }
+
+    /// Read the token from `$TOKEN`, the file named by `$TOKEN_FILE`, or a tokenfile found with
+    /// `search`, along with where it was found
+    fn discover_token(search: &TokenSearch) -> Result<Option<(String, TokenSource)>, Error> {
+        if let Ok(token) = std::env::var("TOKEN") {
+            return Ok(Some((token, TokenSource::Env)));
+        }
+        #[cfg(feature = "dotenv")]
+        if let Some(path) = search.find(&[".env"])? {
+            let token = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|env| dotenv::var(&env, "TOKEN"));
+            if let Some(token) = token {
+                return Ok(Some((token, TokenSource::DotEnv(path))));
+            }
+        }
+        let tokenpath = std::env::var_os("TOKEN_FILE").map(PathBuf::from);
+        if let Some(token) = tokenpath.as_ref().and_then(Self::read_tokenfile) {
+            return Ok(Some((token, TokenSource::EnvFile(tokenpath.unwrap()))));
+        }
+        Ok(Self::find_tokenfile(search)?.and_then(|path| {
+            Self::read_tokenfile(&path).map(|token| (token, TokenSource::Tokenfile(path)))
+        }))
+    }
+
+    /// Read the token again from where it was found, in case it has been changed since this
+    /// instance was created. Returns whether a new token was found, in which case it replaces the
+    /// first token and is used in place of any fallback token
+    #[cfg(not(miri))]
+    fn reload_token(&self) -> Result<bool, Error> {
+        let source = self.tokens.read().unwrap().source.clone();
+        let new = match &source {
+            TokenSource::Given => return Ok(false),
+            TokenSource::Env => std::env::var("TOKEN").ok(),
+            #[cfg(feature = "dotenv")]
+            TokenSource::DotEnv(path) => std::fs::read_to_string(path)
+                .ok()
+                .and_then(|env| dotenv::var(&env, "TOKEN")),
+            TokenSource::EnvFile(path) | TokenSource::Tokenfile(path) => Self::read_tokenfile(path),
+        };
+        let Some(new) = new else {
+            return Ok(false);
         };
+        let mut tokens = self.tokens.write().unwrap();
+        // The reloaded token replaces the one it was originally read as, which may already have
+        // been rejected in favour of a fallback
+        if tokens.list[0] == new {
+            return Ok(false);
+        }
+        tokens.list[0] = new;
+        tokens.active = 0;
+        Ok(true)
+    }
 
-        Self::with_path_and_token(year, path, token)
+    /// Move on to the next fallback token, if there is one. Returns whether there was
+    #[cfg(not(miri))]
+    fn next_token(&self) -> bool {
+        let mut tokens = self.tokens.write().unwrap();
+        if tokens.active + 1 < tokens.list.len() {
+            tokens.active += 1;
+            true
+        } else {
+            false
+        }
     }
 
     /// Construct a new AoC instance in the current user's home directory (see [`dirs::home_dir`]),
-    /// reading the token from `$TOKEN` or `./tokenfile`
+    /// reading the token from `$TOKEN`, the file named by `$TOKEN_FILE`, or `./tokenfile`
     ///
     /// [`dirs::home_dir`]: https://docs.rs/dirs/4.0.0/dirs/fn.home_dir.html
-    ///
-    /// # Panics
-    ///
-    /// Will panic if:
-    /// - `year` is more than 3000 (if this is a problem for you, please open an issue. I'm
-    /// impressed Advent of Code is still going tbh)
     #[cfg(not(miri))]
     pub fn new(year: usize) -> Result<Self, Error> {
         let Some(mut path) = dirs::home_dir() else {
@@ -136,25 +887,54 @@ impl AoC {
         );
     }
 
+    /// Construct a new AoC instance for the most recent Advent of Code, in the same way as
+    /// [`AoC::new`]
+    ///
+    /// During December this is the current year's event, otherwise it is the previous year's.
+    #[cfg(not(miri))]
+    pub fn current() -> Result<Self, Error> {
+        Self::new(latest_year(Utc::now()))
+    }
+
     /// Read the input for the specified day from the cache, or if it is not present, fetch it from
     /// Advent of Code
     ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DayZero`] or [`Error::OutOfBounds`] if `day` isn't between 1 and 25,
+    /// [`Error::EventNotStarted`] if this year's event hasn't started yet, or
+    /// [`Error::NotYetReleased`] if the puzzle for `day` hasn't been released yet, going by the
+    /// [clock](AoC::clock). The year is checked when the instance is created, see
+    /// [`Error::InvalidYear`]. Fetching can fail too, for example with [`Error::Unauthorized`] or
+    /// [`Error::Throttled`].
+    ///
     /// # Panics
     ///
-    /// Will panic if:
-    /// - The puzzle for `day` has not been released yet
-    /// - We are running under miri, and the input is not present in the cache
+    /// Under miri, if the input is not present in the cache
     pub fn read_or_fetch(&self, day: usize) -> Result<String, Error> {
-        if day == 0 {
-            return Err(Error::DayZero);
-        }
-        if day > 25 {
-            return Err(Error::OutOfBounds);
-        }
+        self.read_or_fetch_raw(day)
+            .map(|input| self.apply_transforms(day, input))
+    }
 
+    /// [`AoC::read_or_fetch`], without applying any transforms
+    fn read_or_fetch_raw(&self, day: usize) -> Result<String, Error> {
+        check_day(day)?;
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "emergence.input",
+            year = self.year,
+            day,
+            cache = tracing::field::Empty
+        )
+        .entered();
         if let Some(text) = self.read(day)? {
+            #[cfg(feature = "tracing")]
+            span.record("cache", "hit");
             return Ok(text);
         }
+        #[cfg(feature = "tracing")]
+        span.record("cache", "miss");
 
         #[cfg(miri)]
         {
@@ -163,64 +943,960 @@ impl AoC {
 
         #[cfg(not(miri))]
         {
+            // Only one thread fetches each day at a time. Any others wait for it to finish, then
+            // read what it cached
+            let lock = self
+                .in_flight
+                .lock()
+                .unwrap()
+                .entry(day)
+                .or_default()
+                .clone();
+            let _guard = lock.lock().unwrap();
+            if let Some(text) = self.read(day)? {
+                return Ok(text);
+            }
+
             let text = self.fetch(day)?;
             self.write(day, text.as_str())?;
             Ok(text)
         }
     }
 
-    /// Fetch the input for the specified day from Advent of Code
-    #[cfg(not(miri))]
-    fn fetch(&self, day: usize) -> Result<String, Error> {
-        let starts = DateTime::<FixedOffset>::from_naive_utc_and_offset(
-            NaiveDateTime::new(
-                NaiveDate::from_ymd_opt(self.year as _, 12, day as _).unwrap(),
-                NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-            ),
-            FixedOffset::west_opt(5 * 60 * 60).unwrap(),
-        );
+    /// Like [`AoC::read_or_fetch`], but leaks the input to give it a `'static` lifetime
+    ///
+    /// Each day's input is only leaked once, and later calls (including from clones of this
+    /// instance) return the same string.
+    pub fn read_or_fetch_static(&self, day: usize) -> Result<&'static str, Error> {
+        if let Some(input) = self.leaked.lock().unwrap().get(&day) {
+            return Ok(input);
+        }
+        // Not holding the lock while fetching, so other days aren't held up. Two threads racing
+        // for the same day both leak a copy, but only the first is kept
+        let input = self.read_or_fetch(day)?.leak();
+        Ok(self.leaked.lock().unwrap().entry(day).or_insert(input))
+    }
 
-        if starts > Utc::now() {
-            return Err(Error::NotYetReleased(day));
+    /// Fetch and cache the inputs for every released day that isn't already cached
+    ///
+    /// Failures don't stop the other days from being fetched, except for
+    /// [`Error::Throttled`] and [`Error::CircuitOpen`], after which no more requests are made.
+    #[cfg(not(miri))]
+    pub fn fetch_missing(&self) -> Result<FetchReport, Error> {
+        let mut report = FetchReport::default();
+        for day in 1..=25 {
+            let unlock = match self.unlock_time(day) {
+                // Providers other than Advent of Code may have fewer than 25 days
+                Err(Error::OutOfBounds) => break,
+                res => res?,
+            };
+            if unlock > self.clock.now() {
+                break;
+            }
+            if self.read(day)?.is_some() {
+                report.skipped.push(day);
+                continue;
+            }
+            match self.read_or_fetch_raw(day) {
+                Ok(_) => report.downloaded.push(day),
+                Err(e) => {
+                    let stop = matches!(e, Error::Throttled | Error::CircuitOpen(_));
+                    report.failed.push((day, e));
+                    if stop {
+                        break;
+                    }
+                }
+            }
         }
+        Ok(report)
+    }
 
-        let res = self
-            .client
-            .get(format!(
-                "https://adventofcode.com/{}/day/{}/input",
-                self.year, day
-            ))
-            .header(COOKIE, format!("session={}", self.token))
-            .header(
-                USER_AGENT,
-                "github.com/Sciencentistguy/emergence by jamie@quigley.xyz",
-            )
-            .send()?
-            .error_for_status()?;
-        Ok(res.text()?)
+    /// Diff the input for the specified day against another version of it, such as a friend's
+    /// input or an older copy. To diff against a file, read it with [`std::fs::read_to_string`]
+    /// first.
+    ///
+    /// The input is fetched if it is not already cached, as with [`AoC::read_or_fetch`].
+    pub fn diff_input(&self, day: usize, other: &str) -> Result<InputDiff, Error> {
+        let input = self.read_or_fetch_raw(day)?;
+        Ok(InputDiff::new(&input, other))
     }
 
-    /// Read the input for the specified day from the cache
-    fn read(&self, day: usize) -> io::Result<Option<String>> {
-        let path = self.loc(day);
-        if !path.exists() {
-            return Ok(None);
-        }
-        std::fs::read_to_string(path).map(Some)
+    /// Check the cached input for the specified day for signs that it isn't a real input, or has
+    /// been cut off. Returns `None` if it isn't cached
+    pub fn validate_cached(&self, day: usize) -> Result<Option<Vec<InputProblem>>, Error> {
+        check_day(day)?;
+        Ok(self.read(day)?.as_deref().map(validate::problems))
     }
 
-    /// Read the given text for the specified day to the cache
-    fn write(&self, day: usize, text: &str) -> io::Result<()> {
-        std::fs::write(self.loc(day), text)
+    /// The notes saved for the specified day with [`AoC::set_notes`], if there are any
+    pub fn notes(&self, day: usize) -> Result<Option<String>, Error> {
+        check_day(day)?;
+        Ok(self.fs.read(&self.notes_loc(day))?)
     }
 
-    /// The location of the cached input (or where it would be cached) for the specified day
-    fn loc(&self, day: usize) -> PathBuf {
+    /// Save freeform notes for the specified day, such as the approach taken or anything that
+    /// caught you out, replacing any already saved. They are kept next to the input in the
+    /// cache, as Markdown, and are never removed by [`AoC::gc`]
+    pub fn set_notes(&self, day: usize, notes: &str) -> Result<(), Error> {
+        check_day(day)?;
+        let path = self.notes_loc(day);
+        self.fs.write(&path, notes)?;
+        if self.durable {
+            self.fs.sync(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Gather information about this instance's setup and recent requests, for bug reports.
+    /// Nothing secret is included: the token is only identified by where it came from and a hash
+    pub fn diagnostics(&self) -> Diagnostics {
+        let tokens = self.tokens.read().unwrap();
+        let mode = self.fs.mode(&self.path).ok().flatten();
+        Diagnostics {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            year: self.year,
+            cache_path: self.path.clone(),
+            cache_exists: mode.is_some(),
+            cache_mode: mode,
+            token_source: tokens.source.clone(),
+            token_hash: fingerprint::fnv1a([tokens.active().as_bytes()]),
+            active_token: tokens.active,
+            recent_requests: self.history.get(),
+        }
+    }
+
+    /// Summarise the input for the specified day (as fetched, before any transformations) in a
+    /// way that can be shared publicly, to check whether friends have the same input without
+    /// sharing it. Everyone comparing fingerprints must use the same `salt`
+    pub fn input_fingerprint(&self, day: usize, salt: &str) -> Result<Fingerprint, Error> {
+        Ok(Fingerprint::new(&self.read_or_fetch_raw(day)?, salt))
+    }
+
+    /// Fetch the input for the specified day from Advent of Code, even if it is already cached
+    ///
+    /// If the fetched input differs from the cached copy, this returns [`Error::InputChanged`]
+    /// and leaves the cache untouched, unless `overwrite` is set. A changed input usually means
+    /// the token belongs to a different account, or (rarely) that the puzzle input was corrected.
+    /// The [team cache](AoC::team_cache) is never used, as it may only have the same copy.
+    #[cfg(not(miri))]
+    pub fn refetch(&self, day: usize, overwrite: bool) -> Result<String, Error> {
+        check_day(day)?;
+
+        let text = self.fetch_from(day, false)?;
+        if let Some(cached) = self.read(day)? {
+            if cached == text {
+                return Ok(self.apply_transforms(day, text));
+            }
+            if !overwrite {
+                return Err(Error::InputChanged(InputChange::new(day, &cached, &text)));
+            }
+        }
+        self.write(day, text.as_str())?;
+        Ok(self.apply_transforms(day, text))
+    }
+
+    /// Fetch the input for the specified day from the team cache or the puzzle provider
+    #[cfg(not(miri))]
+    fn fetch(&self, day: usize) -> Result<String, Error> {
+        self.fetch_from(day, true)
+    }
+
+    /// Fetch the input for the specified day from the puzzle provider, trying the team cache first
+    /// if `team_cache` is set
+    #[cfg(not(miri))]
+    fn fetch_from(&self, day: usize, team_cache: bool) -> Result<String, Error> {
+        let now = self.clock.now();
+        if day != 1 && self.unlock_time(1)? > now {
+            return Err(Error::EventNotStarted(self.year));
+        }
+        if self.unlock_time(day)? > now {
+            return Err(Error::NotYetReleased(day));
+        }
+        if let Some(text) = team_cache
+            .then(|| self.fetch_from_team_cache(day))
+            .flatten()
+        {
+            return Ok(text);
+        }
+
+        let text = match self.provider.read_input(self.year, day) {
+            Some(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Error::PuzzleNotFound {
+                    year: self.year,
+                    day,
+                });
+            }
+            Some(text) => text?,
+            None => self
+                .get(&self.provider.input_url(self.year, day))
+                .map_err(self.puzzle_not_found(day))?,
+        };
+
+        // A missing trailing newline is only reported by `validate_cached`, as it could be a
+        // quirk of the input rather than a truncated one
+        let problems = validate::problems(&text);
+        if problems.iter().any(|p| p.is_fatal()) {
+            return Err(Error::InvalidInput { day, problems });
+        }
+        Ok(text)
+    }
+
+    /// Fetch the input for the specified day from the team cache, if one is set. Any failure
+    /// gives `None`, so the input is fetched from the provider instead
+    #[cfg(not(miri))]
+    fn fetch_from_team_cache(&self, day: usize) -> Option<String> {
+        let base = self.team_cache.as_deref().filter(|_| !self.dry_run)?;
+        let url = format!(
+            "{}/{}/day/{}/input",
+            base.trim_end_matches('/'),
+            self.year,
+            day
+        );
+        // Not `AoC::send`, as that would send the token
+        let res = self
+            .recorded(&Method::GET, &url, 0, || {
+                let client = self.client()?;
+                Next {
+                    client,
+                    rest: &self.middleware,
+                }
+                .run(client.get(&url).build()?)
+            })
+            .ok()?;
+        if !res.status().is_success() {
+            return None;
+        }
+        let text = res.text().ok()?;
+        (!validate::problems(&text).iter().any(|p| p.is_fatal())).then_some(text)
+    }
+
+    /// Turns a 404 from a request about the specified day into [`Error::PuzzleNotFound`]
+    #[cfg(not(miri))]
+    fn puzzle_not_found(&self, day: usize) -> impl Fn(Error) -> Error {
+        let year = self.year;
+        move |e| match e {
+            Error::Status {
+                status: StatusCode::NOT_FOUND,
+                ..
+            } => Error::PuzzleNotFound { year, day },
+            e => e,
+        }
+    }
+
+    /// The time at which the puzzle for the specified day unlocks (midnight EST, unless a
+    /// different [`PuzzleProvider`] is set)
+    #[cfg(not(miri))]
+    pub fn unlock_time(&self, day: usize) -> Result<DateTime<FixedOffset>, Error> {
+        check_day(day)?;
+        self.provider
+            .unlock_time(self.year, day)
+            .ok_or(Error::OutOfBounds)
+    }
+
+    /// Render the unlock times of this year's puzzles as an iCalendar (`.ics`) file, to import into
+    /// a calendar app
+    ///
+    /// If `reminder` is set, each event has an alarm that long before the puzzle unlocks. The
+    /// unlock times come from the [`PuzzleProvider`].
+    #[cfg(not(miri))]
+    pub fn unlock_schedule_ics(&self, reminder: Option<std::time::Duration>) -> String {
+        let unlocks: Vec<_> = (1..=25)
+            .map_while(|day| {
+                Some(ics::Unlock {
+                    day,
+                    time: self.provider.unlock_time(self.year, day)?,
+                    input_url: self.provider.input_url(self.year, day),
+                })
+            })
+            .collect();
+        ics::unlock_schedule(
+            self.provider.event_name(),
+            self.year,
+            &unlocks,
+            self.clock.now(),
+            reminder,
+        )
+    }
+
+    /// Wait until the puzzle for the specified day unlocks, then fire a desktop notification.
+    ///
+    /// If `fetch` is set, the input is fetched and cached before notifying, so it is ready to go
+    /// by the time the notification is clicked.
+    #[cfg(all(feature = "notify", not(miri)))]
+    pub fn notify_on_unlock(&self, day: usize, fetch: bool) -> Result<(), Error> {
+        self.wait_for_unlock(day)?;
+
+        let body = if fetch {
+            self.read_or_fetch_raw(day)?;
+            "The input has been fetched and cached"
+        } else {
+            "The puzzle is now available"
+        };
+        notify_rust::Notification::new()
+            .summary(&format!(
+                "{} {} day {} has unlocked",
+                self.provider.event_name(),
+                self.year,
+                day
+            ))
+            .body(body)
+            .show()?;
+        Ok(())
+    }
+
+    /// Fetch the input for the specified day as soon as it unlocks, on a background thread, then
+    /// call `callback` with the result
+    ///
+    /// If the input has already been released, it is fetched (or read from the cache) immediately.
+    /// Advent of Code's clock may be slightly behind ours, so if it replies that the input hasn't
+    /// unlocked yet, the fetch is tried a few more times with a backoff, whatever the retry policy.
+    #[cfg(not(miri))]
+    pub fn schedule_fetch<F>(&self, day: usize, callback: F) -> std::thread::JoinHandle<()>
+    where
+        F: FnOnce(Result<String, Error>) + Send + 'static,
+    {
+        let aoc = self.clone();
+        std::thread::spawn(move || {
+            let res = aoc
+                .wait_for_unlock(day)
+                .and_then(|()| aoc.fetch_at_unlock(day));
+            callback(res);
+        })
+    }
+
+    /// Fetch the input for the specified day once it has unlocked, retrying with a backoff while
+    /// Advent of Code says it is too early
+    #[cfg(not(miri))]
+    fn fetch_at_unlock(&self, day: usize) -> Result<String, Error> {
+        const MAX_ATTEMPTS: u32 = 5;
+        let backoff = RetryPolicy::default();
+        let mut attempt = 1;
+        loop {
+            match self.read_or_fetch(day) {
+                Err(Error::Throttled) if attempt < MAX_ATTEMPTS => {
+                    self.clock.sleep(backoff.delay(attempt));
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Block the current thread until the puzzle for the specified day unlocks
+    #[cfg(not(miri))]
+    fn wait_for_unlock(&self, day: usize) -> Result<(), Error> {
+        let unlock = self.unlock_time(day)?;
+        if let Ok(wait) = (unlock.with_timezone(&Utc) - self.clock.now()).to_std() {
+            self.clock.sleep(wait);
+        }
+        Ok(())
+    }
+
+    /// Check that the input endpoint for the specified day is reachable and accepts the session
+    /// token, without downloading the input
+    ///
+    /// This makes a `HEAD` request, and is not retried. It is intended for health checks. A rejected
+    /// token gives [`Error::Unauthorized`], after trying a reloaded token and any fallback tokens as
+    /// fetching an input would.
+    #[cfg(not(miri))]
+    pub fn probe(&self, day: usize) -> Result<(), Error> {
+        check_day(day)?;
+        let url = self.provider.input_url(self.year, day);
+        self.check_dry_run(&Method::HEAD, &url)?;
+        self.authenticated(|| {
+            let res = self.send(Method::HEAD, &url, 0)?;
+            let status = res.status();
+            // There's no body to look for the login message in
+            if matches!(status, StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED)
+                || is_login_redirect(&res)
+            {
+                return Err(Error::Unauthorized);
+            }
+            if !status.is_success() {
+                return Err(Error::Status {
+                    status,
+                    message: String::new(),
+                });
+            }
+            Ok(())
+        })
+        .map_err(self.puzzle_not_found(day))
+    }
+
+    /// How far the clock (see [`AoC::clock`]) is behind Advent of Code's, going by the `Date`
+    /// header of a `HEAD` request to its home page. Returns `None` if there is no `Date` header
+    ///
+    /// Unlock times are only as accurate as the clock, so a large skew means fetching too early
+    /// or waiting too long. The `Date` header is only accurate to the second.
+    #[cfg(not(miri))]
+    pub fn clock_skew(&self) -> Result<Option<chrono::Duration>, Error> {
+        const URL: &str = "https://adventofcode.com/";
+        self.check_dry_run(&Method::HEAD, URL)?;
+        let res = self.send(Method::HEAD, URL, 0)?;
+        let now = self.clock.now();
+        Ok(res
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.with_timezone(&Utc) - now))
+    }
+
+    /// Fetch this year's calendar page, and parse the star counts and ASCII art from it
+    #[cfg(not(miri))]
+    pub fn calendar(&self) -> Result<Calendar, Error> {
+        Ok(Calendar::parse(self.year, &self.calendar_page()?))
+    }
+
+    /// As [`AoC::calendar`], but serve the cached calendar if there is one, refreshing it in the
+    /// background if it is older than `ttl`
+    #[cfg(not(miri))]
+    pub fn calendar_cached(&self, ttl: std::time::Duration) -> Result<Cached<Calendar>, Error> {
+        let year = self.year;
+        Ok(self
+            .cached_page("calendar.html".into(), ttl, Self::calendar_page)?
+            .map(|html| Calendar::parse(year, &html)))
+    }
+
+    #[cfg(not(miri))]
+    fn calendar_page(&self) -> Result<String, Error> {
+        self.get(&format!("https://adventofcode.com/{}", self.year))
+    }
+
+    /// Fetch the global leaderboard for the specified day, listing the first hundred users to get
+    /// each star
+    #[cfg(not(miri))]
+    pub fn global_leaderboard(&self, day: usize) -> Result<DayLeaderboard, Error> {
+        check_day(day)?;
+        Ok(DayLeaderboard::parse(
+            self.year,
+            day,
+            &self.leaderboard_page(day)?,
+        ))
+    }
+
+    /// As [`AoC::global_leaderboard`], but serve the cached leaderboard if there is one,
+    /// refreshing it in the background if it is older than `ttl`
+    #[cfg(not(miri))]
+    pub fn global_leaderboard_cached(
+        &self,
+        day: usize,
+        ttl: std::time::Duration,
+    ) -> Result<Cached<DayLeaderboard>, Error> {
+        check_day(day)?;
+        let year = self.year;
+        Ok(self
+            .cached_page(format!("leaderboard{day:02}.html"), ttl, move |aoc| {
+                aoc.leaderboard_page(day)
+            })?
+            .map(|html| DayLeaderboard::parse(year, day, &html)))
+    }
+
+    #[cfg(not(miri))]
+    fn leaderboard_page(&self, day: usize) -> Result<String, Error> {
+        self.get(&format!(
+            "https://adventofcode.com/{}/leaderboard/day/{}",
+            self.year, day
+        ))
+        .map_err(self.puzzle_not_found(day))
+    }
+
+    /// Fetch the list of every Advent of Code event so far, oldest first, with the stars collected
+    /// in each. Unlike the rest of this API, this is not specific to this instance's year
+    #[cfg(not(miri))]
+    pub fn events(&self) -> Result<Vec<Event>, Error> {
+        Ok(events::parse(&self.get("https://adventofcode.com/events")?))
+    }
+
+    /// Fetch this year's completion statistics: how many users have one or both stars each day
+    #[cfg(not(miri))]
+    pub fn stats(&self) -> Result<Stats, Error> {
+        Ok(Stats::parse(self.year, &self.stats_page()?))
+    }
+
+    /// As [`AoC::stats`], but serve the cached statistics if there are any, refreshing them in the
+    /// background if they are older than `ttl`
+    #[cfg(not(miri))]
+    pub fn stats_cached(&self, ttl: std::time::Duration) -> Result<Cached<Stats>, Error> {
+        let year = self.year;
+        Ok(self
+            .cached_page("stats.html".into(), ttl, Self::stats_page)?
+            .map(|html| Stats::parse(year, &html)))
+    }
+
+    #[cfg(not(miri))]
+    fn stats_page(&self) -> Result<String, Error> {
+        self.get(&format!("https://adventofcode.com/{}/stats", self.year))
+    }
+
+    /// Read the page cached as `name`, fetching it with `fetch` if it isn't cached yet, or
+    /// refreshing it in the background if it is older than `ttl`.
+    ///
+    /// Pages are cached in the year's directory, with the time they were fetched on the first line
+    #[cfg(not(miri))]
+    fn cached_page<F>(
+        &self,
+        name: String,
+        ttl: std::time::Duration,
+        fetch: F,
+    ) -> Result<Cached<String>, Error>
+    where
+        F: Fn(&AoC) -> Result<String, Error> + Send + 'static,
+    {
+        let path = self.path.join(self.year.to_string()).join(name);
+        let cached = self.fs.read(&path)?.and_then(|page| {
+            let (fetched, html) = page.split_once('\n')?;
+            let fetched = DateTime::parse_from_rfc3339(fetched).ok()?;
+            Some((fetched.with_timezone(&Utc), html.to_owned()))
+        });
+
+        let Some((fetched, html)) = cached else {
+            let html = fetch(self)?;
+            let fetched = self.write_page(&path, &html)?;
+            return Ok(Cached {
+                value: html,
+                fetched,
+                stale: false,
+                refresh: None,
+            });
+        };
+
+        let stale = (self.clock.now() - fetched)
+            .to_std()
+            .is_ok_and(|age| age > ttl);
+        let refresh = stale.then(|| {
+            let aoc = self.clone();
+            std::thread::spawn(move || {
+                aoc.write_page(&path, &fetch(&aoc)?)?;
+                Ok(())
+            })
+        });
+        Ok(Cached {
+            value: html,
+            fetched,
+            stale,
+            refresh,
+        })
+    }
+
+    /// Cache a page fetched now, returning the time it was fetched
+    #[cfg(not(miri))]
+    fn write_page(&self, path: &Path, html: &str) -> Result<DateTime<Utc>, Error> {
+        let fetched = self.clock.now();
+        self.fs
+            .write(path, &format!("{}\n{html}", fetched.to_rfc3339()))?;
+        Ok(fetched)
+    }
+
+    /// Perform an authenticated GET request to Advent of Code, returning the body
+    #[cfg(not(miri))]
+    fn get(&self, url: &str) -> Result<String, Error> {
+        self.check_dry_run(&Method::GET, url)?;
+        self.authenticated(|| self.get_retrying(url))
+    }
+
+    /// In dry-run mode, log the request that would have been made and fail with
+    /// [`Error::DryRun`]
+    #[cfg(not(miri))]
+    fn check_dry_run(&self, method: &Method, url: &str) -> Result<(), Error> {
+        if self.dry_run {
+            log::info!("Dry run: would have requested {method} {url}");
+            return Err(Error::DryRun(url.to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Make a request with `send`, trying it again with a reloaded token and then each fallback
+    /// token for as long as the token is rejected
+    #[cfg(not(miri))]
+    fn authenticated<T, F>(&self, send: F) -> Result<T, Error>
+    where
+        F: Fn() -> Result<T, Error>,
+    {
+        // The user may have just refreshed their session, so it's worth one more try before
+        // falling back to the next token
+        loop {
+            match send() {
+                Err(Error::Unauthorized) if self.reload_token()? || self.next_token() => {}
+                res => return res,
+            }
+        }
+    }
+
+    /// Perform an authenticated GET request, retrying according to the retry policy
+    #[cfg(not(miri))]
+    fn get_retrying(&self, url: &str) -> Result<String, Error> {
+        let mut attempt = 1;
+        loop {
+            match self.get_once(url, attempt - 1) {
+                Err(e)
+                    if attempt < self.retry_policy.max_attempts
+                        && self.retry_policy.is_retryable(&e) =>
+                {
+                    self.clock.sleep(self.retry_policy.delay(attempt));
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Make a single attempt at an authenticated GET request, returning the body. `retry` is the
+    /// number of earlier attempts
+    #[cfg(not(miri))]
+    fn get_once(&self, url: &str, retry: u32) -> Result<String, Error> {
+        let res = self.send(Method::GET, url, retry)?;
+
+        // Read the body before checking the status, as the throttling message can be sent with
+        // either a success or an error status
+        let status = res.status();
+        let text = self.read_body(res, url)?;
+        if is_throttled(&text) {
+            return Err(Error::Throttled);
+        }
+        if is_unauthorized(status, &text) {
+            return Err(Error::Unauthorized);
+        }
+        if !status.is_success() {
+            return Err(Error::Status {
+                status,
+                message: html::message(&text),
+            });
+        }
+        Ok(text)
+    }
+
+    /// Read the body of a response, reporting progress to the progress hook if there is one
+    ///
+    /// If the response has a `Content-Length`, a body of any other length is rejected with
+    /// [`Error::Incomplete`], so a dropped connection can't lead to a truncated input being cached.
+    #[cfg(not(miri))]
+    fn read_body(&self, mut res: Response, url: &str) -> Result<String, Error> {
+        use std::io::Read;
+
+        let total = res.content_length();
+        let mut body = Vec::new();
+        let mut chunk = [0; 8192];
+        loop {
+            let n = match res.read(&mut chunk) {
+                Ok(n) => n,
+                // hyper fails the read if the connection closes early, which is reported below
+                Err(_) if total.is_some() => 0,
+                Err(e) => return Err(e.into()),
+            };
+            if n == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..n]);
+            if let Some(hook) = &self.progress_hook {
+                hook(&Progress {
+                    url,
+                    downloaded: body.len() as u64,
+                    total,
+                });
+            }
+        }
+        if let Some(expected) = total
+            && body.len() as u64 != expected
+        {
+            return Err(Error::Incomplete {
+                expected,
+                received: body.len() as u64,
+            });
+        }
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// Send an authenticated request through the middleware, keeping the circuit breaker and
+    /// audit log up to date. `retry` is the number of earlier attempts at the same request
+    #[cfg(not(miri))]
+    fn send(&self, method: Method, url: &str, retry: u32) -> Result<Response, Error> {
+        if let Some(circuit) = &self.circuit {
+            circuit.check(self.clock.now())?;
+        }
+        let res = self.recorded(&method, url, retry, || {
+            Next {
+                client: self.client()?,
+                rest: &self.middleware,
+            }
+            .run(self.request(method.clone(), url)?.build()?)
+        });
+        if let Some(circuit) = &self.circuit {
+            circuit.record(
+                matches!(&res, Ok(res) if !res.status().is_server_error()),
+                self.clock.now(),
+            );
+        }
+        let res = res?;
+        if let Some(token_cookie) = self.token_cookie(url) {
+            self.cookies.update(res.headers(), token_cookie)?;
+        }
+        Ok(res)
+    }
+
+    /// Make a request with `send`, recording it in the history, audit log and tracing spans
+    #[cfg(not(miri))]
+    fn recorded<F>(
+        &self,
+        method: &Method,
+        url: &str,
+        retry: u32,
+        send: F,
+    ) -> Result<Response, Error>
+    where
+        F: FnOnce() -> Result<Response, Error>,
+    {
+        let method_name = method.to_string();
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "emergence.request",
+            method = method_name,
+            url,
+            status = tracing::field::Empty,
+            error = tracing::field::Empty
+        )
+        .entered();
+        let start = std::time::Instant::now();
+        let res = send();
+        if let Some(log) = &self.audit_log {
+            log.record(&audit::AuditEntry {
+                time: self.clock.now(),
+                method: &method_name,
+                url,
+                outcome: res.as_ref().map(|res| res.status().as_u16()),
+                duration: start.elapsed(),
+                retry,
+            });
+        }
+        self.history.record(RequestRecord {
+            method: method_name,
+            url: url.to_owned(),
+            outcome: match &res {
+                Ok(res) => Ok(res.status().as_u16()),
+                Err(e) => Err(e.to_string()),
+            },
+        });
+        #[cfg(feature = "tracing")]
+        match &res {
+            Ok(res) => span.record("status", res.status().as_u16()),
+            Err(e) => span.record("error", tracing::field::display(e)),
+        };
+        res
+    }
+
+    /// Build a request with any custom headers and request hook applied. Requests to Advent of Code,
+    /// or to a provider with a token cookie, are authenticated; nothing else is sent the token or
+    /// any other cookies
+    #[cfg(not(miri))]
+    fn request(&self, method: Method, url: &str) -> Result<RequestBuilder, Error> {
+        let mut req = self.client()?.request(method, url);
+        if let Some(token_cookie) = self.token_cookie(url) {
+            req = req
+                .header(
+                    COOKIE,
+                    self.cookies
+                        .header(token_cookie, self.tokens.read().unwrap().active()),
+                )
+                .header(
+                    USER_AGENT,
+                    "github.com/Sciencentistguy/emergence by jamie@quigley.xyz",
+                );
+        }
+        let req = req.headers(self.headers.clone());
+        Ok(match &self.request_hook {
+            Some(hook) => hook(req),
+            None => req,
+        })
+    }
+
+    /// The cookie the token is sent in to `url`, if it should be sent at all. Pages like the
+    /// calendar and leaderboards always come from Advent of Code, whatever the input provider
+    #[cfg(not(miri))]
+    fn token_cookie(&self, url: &str) -> Option<&str> {
+        let aoc =
+            reqwest::Url::parse(url).is_ok_and(|url| url.host_str() == Some("adventofcode.com"));
+        if aoc {
+            Some("session")
+        } else {
+            self.provider.token_cookie()
+        }
+    }
+
+    /// The HTTP client, building it if this is the first request
+    #[cfg(not(miri))]
+    fn client(&self) -> Result<&Client, Error> {
+        if let Some(client) = self.client.get() {
+            return Ok(client);
+        }
+        let client = self.connection_options.build()?;
+        Ok(self.client.get_or_init(|| client))
+    }
+
+    /// Back up the whole cache (every year, not just this instance's) into a new timestamped
+    /// snapshot, returning its path
+    ///
+    /// Snapshots are stored inside the cache directory, and can be listed with
+    /// [`AoC::snapshots`] and restored with [`AoC::restore`]. They don't include the git-sync
+    /// history or cookies.
+    #[cfg(not(miri))]
+    pub fn snapshot(&self) -> Result<PathBuf, Error> {
+        let name = self.clock.now().format("%Y%m%dT%H%M%S%.3fZ").to_string();
+        Ok(cache::snapshot(&self.path, &name)?)
+    }
+
+    /// List the paths of the snapshots of the cache, oldest first
+    pub fn snapshots(&self) -> Result<Vec<PathBuf>, Error> {
+        Ok(cache::snapshots(&self.path)?)
+    }
+
+    /// Replace the contents of the cache with a snapshot previously taken with
+    /// [`AoC::snapshot`], one of those listed by [`AoC::snapshots`]. Existing snapshots, the
+    /// git-sync history and cookies are kept, and the cache is left untouched if the snapshot
+    /// can't be copied.
+    pub fn restore(&self, snapshot: impl AsRef<Path>) -> Result<(), Error> {
+        Ok(cache::restore(&self.path, snapshot.as_ref())?)
+    }
+
+    /// Remove old inputs and snapshots from the whole cache (every year, not just this
+    /// instance's) according to `policy`
+    ///
+    /// Like snapshots, this always works on the real filesystem.
+    #[cfg(not(miri))]
+    pub fn gc(&self, policy: &GcPolicy) -> Result<GcReport, Error> {
+        Ok(gc::gc(&self.path, policy, self.clock.now())?)
+    }
+
+    /// Serve this year's inputs over HTTP to anyone who connects to `listener`, without needing a
+    /// token, so that a team or classroom sharing one account only fetches each input once.
+    /// Inputs are served at the same paths as on Advent of Code, `/{year}/day/{day}/input`, and
+    /// are fetched if they aren't already cached. Transforms are not applied
+    ///
+    /// This blocks forever, handling each connection on a new thread. Clients that take more
+    /// than ten seconds to send a request are disconnected, and connections that can't be
+    /// accepted are logged and skipped. There is no authentication, so only listen on a trusted
+    /// network.
+    #[cfg(not(miri))]
+    pub fn serve(&self, listener: std::net::TcpListener) -> Result<(), Error> {
+        let aoc = self.clone();
+        Ok(serve::serve(listener, self.year, move |day| {
+            aoc.read_or_fetch_raw(day)
+        })?)
+    }
+
+    /// Read the input for the specified day from the cache
+    fn read(&self, day: usize) -> io::Result<Option<String>> {
+        self.fs.read(&self.loc(day))
+    }
+
+    /// Read the given text for the specified day to the cache
+    fn write(&self, day: usize, text: &str) -> Result<(), Error> {
+        let path = self.loc(day);
+        self.fs.write(&path, text)?;
+        if self.durable {
+            self.fs.sync(&path)?;
+        }
+        if self.git_sync {
+            let path = path.strip_prefix(&self.path).unwrap();
+            // The input is already cached, so failing now would only make the next call succeed
+            if let Err(e) = git::commit(
+                &self.path,
+                path,
+                &format!("Add input for {} day {}", self.year, day),
+            ) {
+                log::warn!("Could not commit the input for day {day} to git: {e}");
+            }
+        }
+
+        let event = FetchEvent {
+            year: self.year,
+            day,
+            path: &path,
+            input: text,
+        };
+        for hook in &self.fetch_hooks {
+            hook(&event);
+        }
+        Ok(())
+    }
+
+    /// The location of the cached input (or where it would be cached) for the specified day
+    fn loc(&self, day: usize) -> PathBuf {
         let mut path = self.path.clone();
         path.push(self.year.to_string());
         path.push(format!("day{:02}.txt", day));
         path
     }
+
+    /// The location of the notes for the specified day
+    fn notes_loc(&self, day: usize) -> PathBuf {
+        let mut path = self.path.clone();
+        path.push(self.year.to_string());
+        path.push(format!("day{:02}{}", day, cache::NOTES_SUFFIX));
+        path
+    }
+}
+
+/// The time at which the puzzle for `day` of `year` unlocks (midnight EST), if it is a valid date
+#[cfg(not(miri))]
+fn unlock_time(year: i32, day: u32) -> Option<DateTime<FixedOffset>> {
+    Some(DateTime::<FixedOffset>::from_naive_utc_and_offset(
+        NaiveDateTime::new(
+            NaiveDate::from_ymd_opt(year, 12, day)?,
+            NaiveTime::from_hms_opt(5, 0, 0).unwrap(),
+        ),
+        FixedOffset::west_opt(5 * 60 * 60).unwrap(),
+    ))
+}
+
+/// The year of the most recent Advent of Code to have started at `now`
+#[cfg(not(miri))]
+fn latest_year(now: DateTime<Utc>) -> usize {
+    let now = now.with_timezone(&FixedOffset::west_opt(5 * 60 * 60).unwrap());
+    if now.month() == 12 {
+        now.year() as usize
+    } else {
+        now.year() as usize - 1
+    }
+}
+
+/// Whether a response means the session token was not accepted. For inputs, Advent of Code
+/// responds with a 400 and a message asking the user to log in
+#[cfg(not(miri))]
+fn is_unauthorized(status: StatusCode, body: &str) -> bool {
+    status == StatusCode::UNAUTHORIZED
+        || (status.is_client_error() && body.to_ascii_lowercase().contains("please log in"))
+}
+
+/// Whether a response redirects to the login page, or is the login page after following such a
+/// redirect
+#[cfg(not(miri))]
+fn is_login_redirect(res: &Response) -> bool {
+    let location = res
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .map(|location| location.trim_start_matches("https://adventofcode.com"));
+    res.url().path().starts_with("/auth/login")
+        || (res.status().is_redirection()
+            && location.is_some_and(|path| path.starts_with("/auth/login")))
+}
+
+/// Check that `day` is a day on which Advent of Code has a puzzle
+fn check_day(day: usize) -> Result<(), Error> {
+    if day == 0 {
+        return Err(Error::DayZero);
+    }
+    if day > 25 {
+        return Err(Error::OutOfBounds);
+    }
+    Ok(())
+}
+
+/// Whether a response body is Advent of Code's plaintext "stop hammering the server" message
+fn is_throttled(body: &str) -> bool {
+    let body = body.trim_start().to_ascii_lowercase();
+    body.starts_with("you are being rate limited")
+        || body.starts_with("please don't repeatedly request this endpoint")
 }
 
 #[cfg(test)]
@@ -260,10 +1936,30 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
     fn future() {
         let dir = TempDir::new("emergence").unwrap();
-        let _aoc = AoC::with_path(100_000, dir.path()).unwrap();
+        let aoc = AoC::with_path_and_token(100_000, dir.path(), "token".into()).unwrap();
+        assert!(matches!(
+            aoc.read_or_fetch(2),
+            Err(Error::EventNotStarted(100_000))
+        ));
+        assert!(matches!(
+            aoc.read_or_fetch(1),
+            Err(Error::NotYetReleased(1))
+        ));
+        assert!(matches!(
+            AoC::with_path_and_token(usize::MAX, dir.path(), "token".into()),
+            Err(Error::EventNotStarted(_))
+        ));
+    }
+
+    #[test]
+    fn past() {
+        let dir = TempDir::new("emergence").unwrap();
+        assert!(matches!(
+            AoC::with_path(202, dir.path()),
+            Err(Error::InvalidYear(202))
+        ));
     }
 
     #[test]
@@ -279,6 +1975,1076 @@ mod tests {
         assert!(matches!(aoc.read_or_fetch(31), Err(Error::OutOfBounds)));
     }
 
+    #[test]
+    fn unlock_time() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new()).unwrap();
+        assert_eq!(
+            aoc.unlock_time(1).unwrap().with_timezone(&Utc).to_rfc3339(),
+            "2020-12-01T05:00:00+00:00"
+        );
+        assert!(matches!(aoc.unlock_time(26), Err(Error::OutOfBounds)));
+    }
+
+    #[test]
+    fn schedule_fetch_cached() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new()).unwrap();
+        aoc.write(1, "hello").unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        aoc.schedule_fetch(1, move |res| tx.send(res.unwrap()).unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(rx.recv().unwrap(), "hello");
+    }
+
+    #[test]
+    fn schedule_fetch_early_reply() {
+        let clock = Arc::new(ManualClock::new("2020-12-01T04:59:00Z".parse().unwrap()));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .clock(clock.clone())
+            .middleware(move |_, _: Next<'_>| {
+                let mut seen = seen.lock().unwrap();
+                seen.push(clock.now());
+                let body = if seen.len() == 1 {
+                    "Please don't repeatedly request this endpoint before it unlocks! The calendar countdown is synchronized with the server time; the link will be enabled on the calendar the instant this puzzle becomes available.\n"
+                } else {
+                    "input\n"
+                };
+                Ok(http::Response::new(body).into())
+            });
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        aoc.schedule_fetch(1, move |res| tx.send(res.unwrap()).unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(rx.recv().unwrap(), "input\n");
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].to_rfc3339(), "2020-12-01T05:00:00+00:00");
+        assert_eq!(requests[1].to_rfc3339(), "2020-12-01T05:00:01+00:00");
+    }
+
+    #[test]
+    fn dry_run() {
+        struct Logger(Mutex<Vec<String>>);
+        impl log::Log for Logger {
+            fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+                true
+            }
+            fn log(&self, record: &log::Record<'_>) {
+                self.0.lock().unwrap().push(record.args().to_string());
+            }
+            fn flush(&self) {}
+        }
+        static LOGGER: Logger = Logger(Mutex::new(Vec::new()));
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Info);
+
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .dry_run(true);
+        aoc.write(1, "hello").unwrap();
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "hello");
+        assert!(matches!(
+            aoc.read_or_fetch(2),
+            Err(Error::DryRun(url)) if url == "https://adventofcode.com/2020/day/2/input"
+        ));
+        assert!(aoc.read(2).unwrap().is_none());
+        assert!(LOGGER.0.lock().unwrap().contains(
+            &"Dry run: would have requested GET https://adventofcode.com/2020/day/2/input".into()
+        ));
+    }
+
+    #[test]
+    fn custom_headers() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .header(USER_AGENT, HeaderValue::from_static("test"))
+            .request_hook(|req| req.header("x-hooked", "yes"));
+        let req = aoc
+            .request(Method::GET, "https://adventofcode.com/")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()[USER_AGENT], "test");
+        assert_eq!(req.headers()["x-hooked"], "yes");
+        assert!(req.headers().contains_key(COOKIE));
+    }
+
+    #[test]
+    fn latest_year() {
+        let at = |s| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        assert_eq!(super::latest_year(at("2023-12-01T05:00:00Z")), 2023);
+        assert_eq!(super::latest_year(at("2023-12-01T04:59:59Z")), 2022);
+        assert_eq!(super::latest_year(at("2024-01-01T04:59:59Z")), 2023);
+        assert_eq!(super::latest_year(at("2024-07-14T12:00:00Z")), 2023);
+    }
+
+    #[test]
+    fn connection_options() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .connection_options(ConnectionOptions {
+                http2_prior_knowledge: true,
+                tcp_keepalive: Some(std::time::Duration::from_secs(30)),
+                compression: false,
+                pool_max_idle_per_host: 1,
+                system_proxy: false,
+                ..Default::default()
+            })
+            .resolve("adventofcode.com", &["127.0.0.1:443".parse().unwrap()]);
+        assert!(
+            aoc.request(Method::GET, "https://adventofcode.com/")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn root_certificate() {
+        const PEM: &[u8] = b"-----BEGIN CERTIFICATE-----
+MIIBiTCCAS+gAwIBAgIUOLRSeRkzXzytDn8n2jOTlLef7ucwCgYIKoZIzj0EAwIw
+GTEXMBUGA1UEAwwOZW1lcmdlbmNlIHRlc3QwIBcNMjYxMDE1MDQyMDUxWhgPMjEy
+NjA5MjEwNDIwNTFaMBkxFzAVBgNVBAMMDmVtZXJnZW5jZSB0ZXN0MFkwEwYHKoZI
+zj0CAQYIKoZIzj0DAQcDQgAEGm7/UD83xJcO6QD+VqpaW6C0+LRKTh2h1MEWXahx
+xSYPp/ZyWYvxOzQKJci/yX9nS04GA9Mhracm0wXuhvwlkaNTMFEwHQYDVR0OBBYE
+FP1J++lurk06odkYYrXJrJ5ZuaDSMB8GA1UdIwQYMBaAFP1J++lurk06odkYYrXJ
+rJ5ZuaDSMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIgB0gBheHJ
+Gz2iUhuQ1B1jBWrsWXCNTKjKnMLufbAiP3oCIQD5Sglal1Q5rQIxOUCdoJtrQjQ7
+FQq+s/EWJDV3mrdgdA==
+-----END CERTIFICATE-----
+";
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .root_certificate(PEM);
+        assert!(aoc.client().is_ok());
+
+        let aoc = aoc.root_certificate(b"-----BEGIN CERTIFICATE-----\nnonsense\n");
+        assert!(matches!(aoc.client(), Err(Error::Reqwest(_))));
+    }
+
+    #[test]
+    fn middleware() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .middleware(|req: reqwest::blocking::Request, next: Next<'_>| {
+                assert_eq!(req.headers()["x-outer"], "yes");
+                next.run(req)
+            })
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                assert!(req.url().path().ends_with("/input"));
+                Ok(http::Response::new("You are being rate limited.\n").into())
+            })
+            .request_hook(|req| req.header("x-outer", "yes"));
+        assert!(matches!(aoc.read_or_fetch(1), Err(Error::Throttled)));
+    }
+
+    #[test]
+    fn circuit_breaker() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .circuit_breaker(2, std::time::Duration::from_secs(60))
+            .middleware(|_, _: Next<'_>| {
+                let res = http::Response::builder().status(500).body("").unwrap();
+                Ok(res.into())
+            });
+        assert!(matches!(aoc.read_or_fetch(1), Err(Error::Status { .. })));
+        assert!(matches!(aoc.read_or_fetch(1), Err(Error::Status { .. })));
+        assert!(matches!(aoc.read_or_fetch(1), Err(Error::CircuitOpen(_))));
+    }
+
+    #[test]
+    fn retry() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let attempts = Arc::new(AtomicU32::new(0));
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .retry_policy(RetryPolicy {
+                base_delay: std::time::Duration::from_millis(1),
+                ..Default::default()
+            })
+            .middleware({
+                let attempts = attempts.clone();
+                move |_, _: Next<'_>| {
+                    let res = match attempts.fetch_add(1, Ordering::SeqCst) {
+                        0 => http::Response::builder().status(503).body(""),
+                        _ => http::Response::builder().body("1\n2\n"),
+                    };
+                    Ok(res.unwrap().into())
+                }
+            });
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "1\n2\n");
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        attempts.store(0, Ordering::SeqCst);
+        let aoc = aoc.retry_policy(RetryPolicy::none());
+        assert!(aoc.refetch(1, true).is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn audit_log() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let dir = TempDir::new("emergence").unwrap();
+        let log = dir.path().join("audit.jsonl");
+        let attempts = AtomicU32::new(0);
+        let aoc = AoC::with_fs(2020, "cache", "secret".into(), MemoryFs::new())
+            .unwrap()
+            .audit_log(AuditLog::new(&log).unwrap())
+            .retry_policy(RetryPolicy {
+                base_delay: std::time::Duration::from_millis(1),
+                ..Default::default()
+            })
+            .middleware(move |_, _: Next<'_>| {
+                let res = match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 => http::Response::builder().status(503).body(""),
+                    _ => http::Response::builder().body("1\n2\n"),
+                };
+                Ok(res.unwrap().into())
+            });
+        aoc.read_or_fetch(1).unwrap();
+
+        let log = std::fs::read_to_string(&log).unwrap();
+        let lines: Vec<_> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains(r#""day":1,"status":503,"error":null"#));
+        assert!(lines[0].ends_with(r#""retry":0}"#));
+        assert!(lines[1].contains(r#""status":200"#));
+        assert!(lines[1].ends_with(r#""retry":1}"#));
+        assert!(!log.contains("secret"));
+    }
+
+    #[test]
+    fn incomplete_body() {
+        use std::io::{BufRead, BufReader, Write};
+
+        // Only the second response is complete
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for (i, stream) in listener.incoming().take(2).enumerate() {
+                let mut stream = stream.unwrap();
+                let mut line = String::new();
+                let mut reader = BufReader::new(&stream);
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let body = if i == 0 { "1721\n" } else { "1721\n979\n" };
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: 9\r\nConnection: close\r\n\r\n{body}"
+                )
+                .unwrap();
+            }
+        });
+
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .provider(UrlTemplate::new(format!("http://{addr}/{{year}}/{{day}}")));
+        assert!(matches!(
+            aoc.read_or_fetch(1),
+            Err(Error::Incomplete {
+                expected: 9,
+                received: 5
+            })
+        ));
+        assert_eq!(aoc.read(1).unwrap(), None);
+
+        let aoc = aoc.retry_policy(RetryPolicy {
+            base_delay: std::time::Duration::from_millis(1),
+            ..Default::default()
+        });
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "1721\n979\n");
+    }
+
+    #[test]
+    fn single_flight() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let requests = Arc::new(AtomicU32::new(0));
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .middleware({
+                let requests = requests.clone();
+                move |_, _: Next<'_>| {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                    Ok(http::Response::new("input").into())
+                }
+            });
+
+        std::thread::scope(|s| {
+            let threads: Vec<_> = (0..4).map(|_| s.spawn(|| aoc.read_or_fetch(5))).collect();
+            for thread in threads {
+                assert_eq!(thread.join().unwrap().unwrap(), "input");
+            }
+        });
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn fetch_missing() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                let res = match req.url().path() {
+                    "/2020/day/3/input" => http::Response::builder().status(500).body(""),
+                    _ => http::Response::builder().body("input"),
+                };
+                Ok(res.unwrap().into())
+            });
+        aoc.write(1, "cached").unwrap();
+
+        let report = aoc.fetch_missing().unwrap();
+        assert_eq!(report.skipped, [1]);
+        assert_eq!(
+            report.downloaded,
+            (2..=25).filter(|&d| d != 3).collect::<Vec<_>>()
+        );
+        assert!(matches!(report.failed[..], [(3, Error::Status { .. })]));
+        assert_eq!(aoc.read(1).unwrap().unwrap(), "cached");
+    }
+
+    #[test]
+    fn probe() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                assert_eq!(req.method(), Method::HEAD);
+                let status = if req.url().path() == "/2020/day/1/input" {
+                    200
+                } else {
+                    400
+                };
+                let res = http::Response::builder().status(status).body("");
+                Ok(res.unwrap().into())
+            });
+        assert!(aoc.probe(1).is_ok());
+        assert!(matches!(aoc.probe(2), Err(Error::Unauthorized)));
+        assert!(aoc.read(1).unwrap().is_none());
+    }
+
+    #[test]
+    fn probe_fallback_tokens() {
+        let aoc = AoC::with_fs(2020, "cache", "expired".into(), MemoryFs::new())
+            .unwrap()
+            .fallback_tokens(["redirected", "valid"])
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                let res = match req.headers()[COOKIE].to_str().unwrap() {
+                    "session=valid" => http::Response::builder().status(200),
+                    "session=redirected" => http::Response::builder()
+                        .status(302)
+                        .header(LOCATION, "/auth/login"),
+                    _ => http::Response::builder().status(401),
+                };
+                Ok(res.body("").unwrap().into())
+            });
+        assert!(aoc.probe(1).is_ok());
+        assert_eq!(aoc.active_token(), 2);
+    }
+
+    #[test]
+    fn puzzle_not_found() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .middleware(|_, _: Next<'_>| {
+                let res = http::Response::builder().status(404).body("404 Not Found");
+                Ok(res.unwrap().into())
+            });
+        assert!(matches!(
+            aoc.read_or_fetch(3),
+            Err(Error::PuzzleNotFound { year: 2020, day: 3 })
+        ));
+        assert!(matches!(
+            aoc.probe(3),
+            Err(Error::PuzzleNotFound { year: 2020, day: 3 })
+        ));
+    }
+
+    #[test]
+    fn read_or_fetch_static() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new()).unwrap();
+        aoc.write(1, "hello").unwrap();
+
+        let a = aoc.read_or_fetch_static(1).unwrap();
+        let b = aoc.clone().read_or_fetch_static(1).unwrap();
+        assert_eq!(a, "hello");
+        assert!(std::ptr::eq(a, b));
+    }
+
+    #[test]
+    fn read_or_fetch_static_concurrent() {
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let rx = Mutex::new(rx);
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .middleware(move |_, _: Next<'_>| {
+                rx.lock().unwrap().recv().unwrap();
+                Ok(http::Response::new("slow\n").into())
+            });
+        aoc.write(2, "cached\n").unwrap();
+
+        // A slow fetch of one day doesn't hold up the others
+        let slow = std::thread::spawn({
+            let aoc = aoc.clone();
+            move || aoc.read_or_fetch_static(1).unwrap()
+        });
+        assert_eq!(aoc.read_or_fetch_static(2).unwrap(), "cached\n");
+        tx.send(()).unwrap();
+        assert_eq!(slow.join().unwrap(), "slow\n");
+        assert!(std::ptr::eq(
+            aoc.read_or_fetch_static(1).unwrap(),
+            aoc.read_or_fetch_static(1).unwrap()
+        ));
+    }
+
+    #[test]
+    fn transforms() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .transform(|s| s.trim_end().to_owned())
+            .transform_day(2, |s| s.replace('#', "1"));
+        aoc.write(1, "#.#\n").unwrap();
+        aoc.write(2, "#.#\n").unwrap();
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "#.#");
+        assert_eq!(aoc.read_or_fetch(2).unwrap(), "1.1");
+        assert_eq!(aoc.read(2).unwrap().unwrap(), "#.#\n");
+    }
+
+    #[test]
+    fn fetch_hooks() {
+        let dir = TempDir::new("emergence").unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let aoc = AoC::with_path_and_token(2020, dir.path(), "token".into())
+            .unwrap()
+            .middleware(|_, _: Next<'_>| Ok(http::Response::new("input").into()))
+            .on_fetch(move |event| {
+                tx.send((event.day, event.path.to_owned(), event.input.to_owned()))
+                    .unwrap();
+            });
+        aoc.read_or_fetch(4).unwrap();
+        aoc.read_or_fetch(4).unwrap();
+
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            [(4, dir.path().join("2020/day04.txt"), "input".to_owned())]
+        );
+    }
+
+    #[test]
+    fn cookie_jar() {
+        let dir = TempDir::new("emergence").unwrap();
+        let aoc = AoC::with_path_and_token(2020, dir.path(), "TOKEN".into())
+            .unwrap()
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                let res = http::Response::builder()
+                    .header("set-cookie", "extra=1; Path=/")
+                    .body(req.headers()[COOKIE].to_str().unwrap().to_owned());
+                Ok(res.unwrap().into())
+            });
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "session=TOKEN");
+
+        // A fresh instance picks up the cookie from the cache directory
+        let aoc = AoC::with_path_and_token(2020, dir.path(), "TOKEN".into())
+            .unwrap()
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                let cookie = req.headers()[COOKIE].to_str().unwrap().to_owned();
+                Ok(http::Response::new(cookie).into())
+            });
+        assert_eq!(aoc.read_or_fetch(2).unwrap(), "session=TOKEN; extra=1");
+    }
+
+    #[test]
+    fn token_reload() {
+        let aoc = AoC::with_fs(2020, "cache", "OLD".into(), MemoryFs::new())
+            .unwrap()
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                let res = if req.headers()[COOKIE] == "session=OLD" {
+                    http::Response::builder().status(400).body(
+                        "Puzzle inputs differ by user.  Please log in to get your puzzle input.\n",
+                    )
+                } else {
+                    http::Response::builder().body("input")
+                };
+                Ok(res.unwrap().into())
+            });
+        assert!(matches!(aoc.read_or_fetch(1), Err(Error::Unauthorized)));
+
+        // Tokens are only reloaded if they were discovered in the first place
+        let dir = TempDir::new("emergence").unwrap();
+        let path = dir.path().join("tokenfile");
+        std::fs::write(&path, "OLD\n").unwrap();
+        aoc.tokens.write().unwrap().source = TokenSource::Tokenfile(path.clone());
+        assert!(matches!(aoc.read_or_fetch(1), Err(Error::Unauthorized)));
+
+        std::fs::write(&path, "NEW\n").unwrap();
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "input");
+        assert_eq!(aoc.tokens.read().unwrap().active(), "NEW");
+    }
+
+    #[test]
+    fn trace() {
+        let dir = TempDir::new("emergence").unwrap();
+        let log = dir.path().join("trace.log");
+        let aoc = AoC::with_path_and_token(2020, dir.path().join("cache"), "secret".into())
+            .unwrap()
+            .middleware(Trace::new(&log).unwrap().max_body(4))
+            .middleware(|_: reqwest::blocking::Request, _: Next<'_>| {
+                Ok(http::Response::builder()
+                    .header("set-cookie", "ru=abc; Path=/")
+                    .body("1721\n979\n")
+                    .unwrap()
+                    .into())
+            });
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "1721\n979\n");
+
+        let trace = std::fs::read_to_string(log).unwrap();
+        assert!(trace.starts_with("> GET https://adventofcode.com/2020/day/1/input\n"));
+        assert!(trace.contains("> cookie: session=<redacted>\n"));
+        assert!(trace.contains("< set-cookie: ru=<redacted>; Path=/\n"));
+        assert!(trace.contains("< 200 OK ("));
+        assert!(trace.contains("1721\n[5 more bytes]\n"));
+        assert!(!trace.contains("secret"));
+    }
+
+    #[test]
+    fn cassette() {
+        let dir = TempDir::new("emergence").unwrap();
+        let cassette = dir.path().join("cassettes/2020");
+        let record = AoC::with_path_and_token(2020, dir.path().join("record"), "token".into())
+            .unwrap()
+            .middleware(Cassette::new(&cassette).unwrap())
+            .middleware(|_: reqwest::blocking::Request, _: Next<'_>| {
+                Ok(http::Response::builder()
+                    .header("set-cookie", "session=new")
+                    .body("1721\n979\n")
+                    .unwrap()
+                    .into())
+            });
+        assert_eq!(record.read_or_fetch(1).unwrap(), "1721\n979\n");
+        assert!(
+            !std::fs::read_to_string(&cassette)
+                .unwrap()
+                .contains("session")
+        );
+
+        // With nothing behind the cassette, requests would go to the network
+        let replay = AoC::with_path_and_token(2020, dir.path().join("replay"), "token".into())
+            .unwrap()
+            .middleware(Cassette::new(&cassette).unwrap());
+        assert_eq!(replay.read_or_fetch(1).unwrap(), "1721\n979\n");
+        assert!(matches!(
+            replay.read_or_fetch(2),
+            Err(Error::NotRecorded(_))
+        ));
+    }
+
+    #[test]
+    fn manual_clock() {
+        let clock = Arc::new(ManualClock::new("2020-12-01T04:59:59Z".parse().unwrap()));
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .clock(clock.clone())
+            .middleware(|_, _: Next<'_>| Ok(http::Response::new("input").into()));
+        assert!(matches!(
+            aoc.read_or_fetch(1),
+            Err(Error::NotYetReleased(1))
+        ));
+
+        // Waiting for the unlock advances the clock rather than sleeping
+        let (tx, rx) = std::sync::mpsc::channel();
+        aoc.schedule_fetch(1, move |res| tx.send(res.unwrap()).unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(rx.recv().unwrap(), "input");
+        assert_eq!(
+            clock.now(),
+            "2020-12-01T05:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+
+        // Events that haven't really started can be simulated too
+        let aoc = AoC::with_fs(2100, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .clock(ManualClock::new("2100-12-02T05:00:00Z".parse().unwrap()))
+            .middleware(|_, _: Next<'_>| Ok(http::Response::new("input").into()));
+        assert_eq!(aoc.read_or_fetch(2).unwrap(), "input");
+    }
+
+    #[test]
+    fn memory_fs() {
+        let fs = Arc::new(MemoryFs::new());
+        let aoc = AoC::with_fs(2020, "memory-cache", "token".into(), fs.clone())
+            .unwrap()
+            .middleware(|_, _: Next<'_>| Ok(http::Response::new("input").into()));
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "input");
+        assert_eq!(
+            fs.read(Path::new("memory-cache/2020/day01.txt")).unwrap(),
+            Some("input".into())
+        );
+        assert!(!Path::new("memory-cache").exists());
+    }
+
+    #[test]
+    fn progress() {
+        let input = "1\n".repeat(10_000);
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .middleware({
+                let input = input.clone();
+                move |_, _: Next<'_>| {
+                    Ok(http::Response::builder()
+                        .header("content-length", input.len())
+                        .body(input.clone())
+                        .unwrap()
+                        .into())
+                }
+            });
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let aoc = aoc.on_progress({
+            let progress = progress.clone();
+            move |p| progress.lock().unwrap().push((p.downloaded, p.total))
+        });
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), input);
+
+        let progress = progress.lock().unwrap();
+        assert!(progress.len() > 1);
+        assert!(progress.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(*progress.last().unwrap(), (20_000, Some(20_000)));
+    }
+
+    #[test]
+    fn stale_while_revalidate() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let clock = Arc::new(ManualClock::new("2021-01-01T00:00:00Z".parse().unwrap()));
+        let requests = Arc::new(AtomicU32::new(0));
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .clock(clock.clone())
+            .middleware({
+                let requests = requests.clone();
+                move |_, _: Next<'_>| {
+                    let n = requests.fetch_add(1, Ordering::SeqCst) + 1;
+                    let page = format!("<pre class=\"calendar\">version {n}</pre>");
+                    Ok(http::Response::new(page).into())
+                }
+            });
+        let ttl = std::time::Duration::from_secs(60);
+
+        let cal = aoc.calendar_cached(ttl).unwrap();
+        assert!(!cal.stale && cal.refresh.is_none());
+        assert_eq!(cal.value.art, "version 1");
+
+        clock.advance(ttl);
+        let cal = aoc.calendar_cached(ttl).unwrap();
+        assert!(!cal.stale);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // The stale copy is served while the refresh happens
+        clock.advance(std::time::Duration::from_secs(1));
+        let cal = aoc.calendar_cached(ttl).unwrap();
+        assert!(cal.stale);
+        assert_eq!(cal.value.art, "version 1");
+        cal.refresh.unwrap().join().unwrap().unwrap();
+
+        let cal = aoc.calendar_cached(ttl).unwrap();
+        assert!(!cal.stale);
+        assert_eq!(cal.value.art, "version 2");
+        assert_eq!(cal.fetched, clock.now());
+    }
+
+    #[test]
+    fn durable() {
+        let dir = TempDir::new("emergence").unwrap();
+        let aoc = AoC::with_path_and_token(2020, dir.path(), "token".into())
+            .unwrap()
+            .durable(true)
+            .middleware(|_, _: Next<'_>| Ok(http::Response::new("input").into()));
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "input");
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("2020/day01.txt")).unwrap(),
+            "input"
+        );
+    }
+
+    #[test]
+    fn validation() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                let body = match req.url().path() {
+                    "/2020/day/1/input" => "",
+                    "/2020/day/2/input" => "<!DOCTYPE html>\n<html><body>Log in</body></html>\n",
+                    _ => "1\n2",
+                };
+                Ok(http::Response::new(body).into())
+            });
+        assert!(matches!(
+            aoc.read_or_fetch(1),
+            Err(Error::InvalidInput { day: 1, problems }) if problems == [InputProblem::Empty]
+        ));
+        assert!(matches!(
+            aoc.read_or_fetch(2),
+            Err(Error::InvalidInput { day: 2, .. })
+        ));
+        assert_eq!(aoc.validate_cached(2).unwrap(), None);
+
+        assert_eq!(aoc.read_or_fetch(3).unwrap(), "1\n2");
+        assert_eq!(
+            aoc.validate_cached(3).unwrap(),
+            Some(vec![InputProblem::Unterminated])
+        );
+    }
+
+    #[test]
+    fn diagnostics() {
+        let aoc = AoC::with_fs(2020, "cache", "secret".into(), MemoryFs::new())
+            .unwrap()
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                let status = if req.url().path() == "/2020/day/1/input" {
+                    200
+                } else {
+                    404
+                };
+                Ok(http::Response::builder()
+                    .status(status)
+                    .body("1\n")
+                    .unwrap()
+                    .into())
+            });
+        aoc.read_or_fetch(1).unwrap();
+        assert!(aoc.read_or_fetch(2).is_err());
+
+        let diagnostics = aoc.diagnostics();
+        assert_eq!(diagnostics.token_source, TokenSource::Given);
+        assert!(!diagnostics.cache_exists);
+        assert_eq!(
+            diagnostics
+                .recent_requests
+                .iter()
+                .map(|r| r.outcome.clone())
+                .collect::<Vec<_>>(),
+            [Ok(200), Ok(404)]
+        );
+        let report = diagnostics.to_string();
+        assert!(report.contains("GET https://adventofcode.com/2020/day/2/input -> 404\n"));
+        assert!(!report.contains("secret"));
+    }
+
+    #[test]
+    fn provider() {
+        struct Quests;
+        impl PuzzleProvider for Quests {
+            fn input_url(&self, year: usize, day: usize) -> String {
+                format!("https://quests.example/{year}/quest/{day}")
+            }
+            fn unlock_time(&self, year: usize, day: usize) -> Option<DateTime<FixedOffset>> {
+                (day <= 20).then(|| AdventOfCode.unlock_time(year, day).unwrap())
+            }
+            fn token_cookie(&self) -> Option<&str> {
+                Some("quests")
+            }
+            fn event_name(&self) -> &str {
+                "Quests"
+            }
+        }
+
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .provider(Quests)
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                assert_eq!(req.headers()[COOKIE], "quests=token");
+                Ok(http::Response::new(format!("{}\n", req.url().path())).into())
+            });
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "/2020/quest/1\n");
+        assert!(matches!(aoc.unlock_time(21), Err(Error::OutOfBounds)));
+        let ics = aoc.unlock_schedule_ics(None);
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 20);
+        assert!(ics.contains("URL:https://quests.example/2020/quest/20\r\n"));
+        assert!(ics.contains("SUMMARY:Quests 2020 day 20\r\n"));
+        assert!(!ics.contains("Advent of Code"));
+
+        let report = aoc.fetch_missing().unwrap();
+        assert_eq!(report.skipped, [1]);
+        assert_eq!(report.downloaded, (2..=20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn local_directory() {
+        let dir = TempDir::new("emergence").unwrap();
+        std::fs::create_dir(dir.path().join("2020")).unwrap();
+        std::fs::write(dir.path().join("2020/day01.txt"), "practice\n").unwrap();
+
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .provider(LocalDirectory::new(dir.path()));
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "practice\n");
+        assert!(matches!(
+            aoc.read_or_fetch(2),
+            Err(Error::PuzzleNotFound { year: 2020, day: 2 })
+        ));
+    }
+
+    #[test]
+    fn url_template() {
+        let aoc = AoC::with_fs(2020, "cache", "secret".into(), MemoryFs::new())
+            .unwrap()
+            .provider(UrlTemplate::new("https://example.com/{year}/{day}.txt"))
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                // The token must never leave Advent of Code
+                assert!(!req.headers().contains_key(COOKIE));
+                assert!(!req.headers().contains_key(USER_AGENT));
+                let res = http::Response::builder()
+                    .header("set-cookie", "tracking=1; Path=/")
+                    .body(format!("{}\n", req.url().path()));
+                Ok(res.unwrap().into())
+            });
+        assert_eq!(aoc.read_or_fetch(7).unwrap(), "/2020/7.txt\n");
+        assert_eq!(aoc.cookies.header("session", "secret"), "session=secret");
+    }
+
+    #[test]
+    fn url_template_aoc_pages() {
+        let aoc = AoC::with_fs(2020, "cache", "secret".into(), MemoryFs::new())
+            .unwrap()
+            .provider(UrlTemplate::new("https://example.com/{year}/{day}.txt"))
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                // Only the inputs come from the template
+                assert_eq!(req.url().as_str(), "https://adventofcode.com/2020");
+                assert_eq!(req.headers()[COOKIE], "session=secret");
+                assert!(req.headers().contains_key(USER_AGENT));
+                let html = "<pre class=\"calendar\">\n<a aria-label=\"Day 1, two stars\" href=\"/2020/day/1\">1</a>\n</pre>\n";
+                Ok(http::Response::new(html).into())
+            });
+        aoc.calendar().unwrap();
+    }
+
+    #[test]
+    fn serve() {
+        use std::io::{Read, Write};
+
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .transform(|s| s.to_uppercase())
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                let res = match req.url().path() {
+                    "/2020/day/1/input" => http::Response::builder().body("input\n"),
+                    _ => http::Response::builder().status(404).body(""),
+                };
+                Ok(res.unwrap().into())
+            });
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || aoc.serve(listener));
+
+        let get = |path: &str| {
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {path} HTTP/1.1\r\nHost: cache\r\n\r\n").unwrap();
+            let mut res = String::new();
+            stream.read_to_string(&mut res).unwrap();
+            res
+        };
+        let res = get("/2020/day/1/input");
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(res.ends_with("\r\n\r\ninput\n"));
+        assert!(get("/2020/day/2/input").starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(get("/2019/day/1/input").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn git_sync_failure() {
+        let dir = TempDir::new("emergence").unwrap();
+        let aoc = AoC::with_path_and_token(2020, dir.path(), "token".into())
+            .unwrap()
+            .git_sync(true)
+            .middleware(|_, _: Next<'_>| Ok(http::Response::new("input\n").into()));
+        // Not a repository that git can use
+        std::fs::write(dir.path().join(".git"), "broken").unwrap();
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "input\n");
+    }
+
+    #[test]
+    fn team_cache() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .team_cache("http://team.example/")
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                let body = match (req.url().host_str(), req.url().path()) {
+                    (Some("team.example"), "/2020/day/1/input") => {
+                        assert!(!req.headers().contains_key(COOKIE));
+                        "from team\n"
+                    }
+                    (Some("team.example"), _) => "",
+                    _ => "from aoc\n",
+                };
+                Ok(http::Response::new(body).into())
+            });
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "from team\n");
+        assert_eq!(aoc.read_or_fetch(2).unwrap(), "from aoc\n");
+        assert_eq!(
+            aoc.diagnostics().recent_requests[0].url,
+            "http://team.example/2020/day/1/input"
+        );
+
+        // Refetching is about what Advent of Code has now, not what the team already has
+        assert!(matches!(aoc.refetch(1, false), Err(Error::InputChanged(_))));
+    }
+
+    #[test]
+    fn notes() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new()).unwrap();
+        assert_eq!(aoc.notes(1).unwrap(), None);
+        aoc.set_notes(1, "Two-sum with a HashSet\n").unwrap();
+        assert_eq!(
+            aoc.notes(1).unwrap().as_deref(),
+            Some("Two-sum with a HashSet\n")
+        );
+        assert_eq!(aoc.notes(2).unwrap(), None);
+        assert!(matches!(aoc.set_notes(0, ""), Err(Error::DayZero)));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn cache_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("emergence").unwrap();
+        let mode = |path: &str| {
+            std::fs::metadata(dir.path().join(path))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777
+        };
+        let aoc = AoC::with_path_and_token(2020, dir.path().join("cache"), "token".into())
+            .unwrap()
+            .cache_permissions(0o700, 0o600)
+            .middleware(|_: reqwest::blocking::Request, _: Next<'_>| {
+                Ok(http::Response::builder()
+                    .header("set-cookie", "ru=abc")
+                    .body("input\n")
+                    .unwrap()
+                    .into())
+            });
+        aoc.read_or_fetch(1).unwrap();
+        assert_eq!(mode("cache/2020/day01.txt"), 0o600);
+        assert_eq!(mode("cache/2020"), 0o700);
+        assert_eq!(mode("cache/cookies"), 0o600);
+        assert_eq!(mode("cache"), 0o700);
+        assert_eq!(aoc.diagnostics().cache_mode, Some(0o700));
+        assert!(aoc.diagnostics().to_string().contains("(mode 700)"));
+    }
+
+    #[test]
+    fn clock_skew() {
+        let aoc = AoC::with_fs(2020, "cache", "token".into(), MemoryFs::new())
+            .unwrap()
+            .clock(ManualClock::new("2020-12-01T05:00:00Z".parse().unwrap()))
+            .middleware(|_: reqwest::blocking::Request, _: Next<'_>| {
+                Ok(http::Response::builder()
+                    .header("date", "Tue, 01 Dec 2020 05:00:30 GMT")
+                    .body("")
+                    .unwrap()
+                    .into())
+            });
+        assert_eq!(
+            aoc.clock_skew().unwrap(),
+            Some(chrono::Duration::seconds(30))
+        );
+    }
+
+    #[test]
+    fn fallback_tokens() {
+        let aoc = AoC::with_fs(2020, "cache", "expired".into(), MemoryFs::new())
+            .unwrap()
+            .fallback_tokens(["also expired", "valid", "unused"])
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                let res = if req.headers()[COOKIE] == "session=valid" {
+                    http::Response::builder().body("input")
+                } else {
+                    http::Response::builder().status(400).body(
+                        "Puzzle inputs differ by user.  Please log in to get your puzzle input.\n",
+                    )
+                };
+                Ok(res.unwrap().into())
+            });
+        assert_eq!(aoc.active_token(), 0);
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "input");
+        assert_eq!(aoc.active_token(), 2);
+        assert_eq!(aoc.clone().active_token(), 2);
+    }
+
+    #[test]
+    fn fallback_token_reload() {
+        let dir = TempDir::new("emergence").unwrap();
+        let path = dir.path().join("tokenfile");
+        std::fs::write(&path, "expired\n").unwrap();
+        let aoc = AoC::with_fs(2020, "cache", "expired".into(), MemoryFs::new())
+            .unwrap()
+            .fallback_tokens(["fallback"])
+            .middleware(|req: reqwest::blocking::Request, _: Next<'_>| {
+                let cookie = &req.headers()[COOKIE];
+                let res = if cookie == "session=renewed"
+                    || (cookie == "session=fallback" && req.url().path().ends_with("/1/input"))
+                {
+                    http::Response::builder().body("input")
+                } else {
+                    http::Response::builder().status(400).body(
+                        "Puzzle inputs differ by user.  Please log in to get your puzzle input.\n",
+                    )
+                };
+                Ok(res.unwrap().into())
+            });
+        aoc.tokens.write().unwrap().source = TokenSource::Tokenfile(path.clone());
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), "input");
+        assert_eq!(aoc.active_token(), 1);
+
+        // Reloading the rejected first token doesn't replace the fallback
+        assert!(matches!(aoc.read_or_fetch(2), Err(Error::Unauthorized)));
+        assert_eq!(aoc.tokens.read().unwrap().list, ["expired", "fallback"]);
+
+        std::fs::write(&path, "renewed\n").unwrap();
+        assert_eq!(aoc.read_or_fetch(2).unwrap(), "input");
+        assert_eq!(aoc.active_token(), 0);
+        assert_eq!(aoc.tokens.read().unwrap().list, ["renewed", "fallback"]);
+    }
+
+    #[test]
+    fn throttled() {
+        assert!(is_throttled(
+            "Please don't repeatedly request this endpoint before it unlocks! The calendar countdown is synchronized with the server time; the link will be enabled on the calendar the instant this puzzle becomes available.\n"
+        ));
+        assert!(is_throttled("You are being rate limited.\n"));
+        assert!(!is_throttled("1721\n979\n366\n"));
+    }
+
+    #[test]
+    fn input_change() {
+        let change = InputChange::new(1, "1\n2\n3\n", "1\n5\n3\n4\n");
+        assert_eq!(change.cached_lines, 3);
+        assert_eq!(change.fetched_lines, 4);
+        assert_eq!(change.differing_lines, 2);
+        assert_ne!(change.cached_hash, change.fetched_hash);
+
+        // The hashes must not change between versions
+        let change = InputChange::new(1, "", "a");
+        assert_eq!(change.cached_hash, 0xcbf29ce484222325);
+        assert_eq!(change.fetched_hash, 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn reads_tokenfile() {
+        let dir = TempDir::new("emergence").unwrap();
+        let path = dir.path().join("aoc_session");
+        std::fs::write(&path, "TESTTOKEN\n").unwrap();
+        assert_eq!(AoC::read_tokenfile(&path).unwrap(), "TESTTOKEN");
+        assert!(AoC::read_tokenfile(dir.path().join("missing")).is_none());
+    }
+
     #[test]
     fn finds_tokenfile() {
         let cwd = std::env::current_dir().unwrap();
@@ -288,14 +3054,15 @@ mod tests {
         std::env::set_current_dir(&dir).unwrap();
 
         std::fs::write(dir.join("tokenfile"), "TESTTOKEN").unwrap();
-        assert!(AoC::find_tokenfile().unwrap().is_some());
+        let search = TokenSearch::default();
+        assert!(AoC::find_tokenfile(&search).unwrap().is_some());
 
         dir.push("a");
         dir.push("b");
         std::fs::create_dir_all(&dir).unwrap();
         std::env::set_current_dir(&dir).unwrap();
 
-        assert!(AoC::find_tokenfile().unwrap().is_some());
+        assert!(AoC::find_tokenfile(&search).unwrap().is_some());
 
         std::env::set_current_dir(cwd).unwrap();
     }
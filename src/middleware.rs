@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use reqwest::{
+    StatusCode,
+    blocking::{Client, Request, Response},
+    header::HeaderMap,
+};
+
+use crate::Error;
+
+/// A layer wrapped around every request to Advent of Code, added with
+/// [`AoC::middleware`](crate::AoC::middleware)
+///
+/// Middleware can inspect or modify the request, pass it on with [`Next::run`], and inspect the
+/// result. It can also skip the rest of the chain entirely and return its own response (for
+/// example from a cache) or error (for example to inject faults in tests).
+///
+/// Closures with the same signature as [`Middleware::handle`] implement this trait.
+pub trait Middleware: Send + Sync + 'static {
+    fn handle(&self, req: Request, next: Next<'_>) -> Result<Response, Error>;
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(Request, Next<'_>) -> Result<Response, Error> + Send + Sync + 'static,
+{
+    fn handle(&self, req: Request, next: Next<'_>) -> Result<Response, Error> {
+        self(req, next)
+    }
+}
+
+/// The rest of the middleware chain, ending with actually sending the request
+pub struct Next<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) rest: &'a [Arc<dyn Middleware>],
+}
+
+impl Next<'_> {
+    /// Pass the request on to the next middleware, or send it if this is the last one
+    pub fn run(self, req: Request) -> Result<Response, Error> {
+        match self.rest.split_first() {
+            Some((middleware, rest)) => middleware.handle(
+                req,
+                Next {
+                    client: self.client,
+                    rest,
+                },
+            ),
+            None => Ok(self.client.execute(req)?),
+        }
+    }
+}
+
+/// A response read fully into memory, so that middleware can inspect its body and still pass it on
+pub(crate) struct Buffered {
+    pub(crate) status: StatusCode,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Buffered {
+    pub(crate) fn read(res: Response) -> Result<Self, Error> {
+        Ok(Self {
+            status: res.status(),
+            headers: res.headers().clone(),
+            body: res.bytes()?.to_vec(),
+        })
+    }
+
+    /// Build an identical response to the one that was read
+    pub(crate) fn into_response(self) -> Response {
+        let mut res = http::Response::builder().status(self.status);
+        *res.headers_mut().unwrap() = self.headers;
+        res.body(self.body).unwrap().into()
+    }
+}
@@ -0,0 +1,240 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{Fs, OsFs};
+
+/// The first line of every pack, identifying the format and its version
+const HEADER: &str = "emergence-pack 1";
+
+/// A cache stored in a single file, rather than one file per input. Use it with
+/// [`AoC::with_fs`](crate::AoC::with_fs), normally with one pack per year and an empty cache path:
+///
+/// ```no_run
+/// # use emergence::{AoC, PackFs};
+/// let aoc = AoC::with_fs(2022, "", "token".into(), PackFs::open("2022.pack")?)?;
+/// # Ok::<(), emergence::Error>(())
+/// ```
+///
+/// One file is much friendlier to sync tools, backups and network filesystems than hundreds of
+/// tiny ones. The pack is plain text: a header line, then an index with a line of
+/// `path<TAB>offset<TAB>length` for each file, a blank line, and the contents of every file one
+/// after another. Offsets are in bytes from the start of the contents.
+///
+/// The whole pack is held in memory, and is rewritten to a temporary file and renamed into place
+/// on every change, so it is never left half-written. Only one instance should have a pack open at
+/// a time, as each would overwrite the other's changes.
+#[derive(Debug)]
+pub struct PackFs {
+    path: PathBuf,
+    files: Mutex<BTreeMap<String, String>>,
+}
+
+impl PackFs {
+    /// Open the pack at `path`, which is created when the first file is written to it
+    ///
+    /// # Errors
+    ///
+    /// If the pack exists but can't be read or isn't a valid pack
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+        let files = match OsFs.read(&path)? {
+            Some(pack) => parse(&pack).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "malformed cache pack")
+            })?,
+            None => BTreeMap::new(),
+        };
+        Ok(Self {
+            path,
+            files: Mutex::new(files),
+        })
+    }
+
+    fn save(&self, files: &BTreeMap<String, String>) -> io::Result<()> {
+        let mut name = self.path.file_name().unwrap_or_default().to_owned();
+        name.push(".tmp");
+        let tmp = self.path.with_file_name(name);
+        let mut file = std::fs::File::create(&tmp)?;
+        io::Write::write_all(&mut file, serialize(files).as_bytes())?;
+        // Otherwise a crash could leave the renamed pack without its contents
+        file.sync_all()?;
+        std::fs::rename(tmp, &self.path)
+    }
+}
+
+impl Fs for PackFs {
+    fn read(&self, path: &Path) -> io::Result<Option<String>> {
+        Ok(self.files.lock().unwrap().get(&key(path)?).cloned())
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        let key = key(path)?;
+        let mut files = self.files.lock().unwrap();
+        // Rewriting the pack for nothing would make sync tools transfer it again
+        if files.get(&key).is_some_and(|old| old == contents) {
+            return Ok(());
+        }
+        let old = files.insert(key.clone(), contents.to_owned());
+        let saved = self.save(&files);
+        if saved.is_err() {
+            // Keep memory consistent with the pack on disk
+            match old {
+                Some(old) => files.insert(key, old),
+                None => files.remove(&key),
+            };
+        }
+        saved
+    }
+
+    fn create_dir_all(&self, _: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn sync(&self, _: &Path) -> io::Result<()> {
+        // Nothing has been written if the pack doesn't exist yet
+        if self.path.exists() {
+            OsFs.sync(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// The name `path` is stored under in the index: its components separated by `/`
+fn key(path: &Path) -> io::Result<String> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} can't be stored in a cache pack", path.display()),
+        )
+    };
+    let mut key = String::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(name) => {
+                let name = name.to_str().ok_or_else(invalid)?;
+                if name.contains(['\t', '\n']) {
+                    return Err(invalid());
+                }
+                if !key.is_empty() {
+                    key.push('/');
+                }
+                key.push_str(name);
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(key)
+}
+
+fn serialize(files: &BTreeMap<String, String>) -> String {
+    let mut pack = format!("{HEADER}\n");
+    let mut offset = 0;
+    for (path, contents) in files {
+        writeln!(pack, "{path}\t{offset}\t{}", contents.len()).unwrap();
+        offset += contents.len();
+    }
+    pack.push('\n');
+    files.values().for_each(|contents| pack.push_str(contents));
+    pack
+}
+
+fn parse(pack: &str) -> Option<BTreeMap<String, String>> {
+    let rest = pack.strip_prefix(HEADER)?.strip_prefix('\n')?;
+    let (index, contents) = match rest.strip_prefix('\n') {
+        Some(contents) => ("", contents),
+        None => rest.split_once("\n\n")?,
+    };
+    index
+        .lines()
+        .map(|line| {
+            let mut fields = line.split('\t');
+            let path = fields.next()?;
+            let offset: usize = fields.next()?.parse().ok()?;
+            let len: usize = fields.next()?.parse().ok()?;
+            let file = contents.get(offset..offset.checked_add(len)?)?;
+            Some((path.to_owned(), file.to_owned()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::AoC;
+
+    #[test]
+    fn round_trip() {
+        let dir = TempDir::new("emergence").unwrap();
+        let path = dir.path().join("2020.pack");
+        let pack = PackFs::open(&path).unwrap();
+        assert_eq!(pack.read(Path::new("2020/day01")).unwrap(), None);
+
+        pack.write(Path::new("2020/day01"), "1721\n979\n").unwrap();
+        pack.write(Path::new("./2020/day02"), "").unwrap();
+        pack.write(Path::new("2020/day01.notes.md"), "# Day 1\n\n")
+            .unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            text,
+            "emergence-pack 1\n2020/day01\t0\t9\n2020/day01.notes.md\t9\t9\n2020/day02\t18\t0\n\n1721\n979\n# Day 1\n\n"
+        );
+
+        let pack = PackFs::open(&path).unwrap();
+        assert_eq!(
+            pack.read(Path::new("2020/day01")).unwrap().as_deref(),
+            Some("1721\n979\n")
+        );
+        assert_eq!(
+            pack.read(Path::new("2020/day01.notes.md"))
+                .unwrap()
+                .as_deref(),
+            Some("# Day 1\n\n")
+        );
+        assert_eq!(
+            pack.read(Path::new("2020/day02")).unwrap().as_deref(),
+            Some("")
+        );
+        assert!(pack.write(Path::new("../day03"), "").is_err());
+        assert!(pack.write(Path::new("day\t03"), "").is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        let dir = TempDir::new("emergence").unwrap();
+        let path = dir.path().join("2020.pack");
+        for pack in [
+            "",
+            "emergence-pack 2\n\n",
+            "emergence-pack 1\nday01\t0\t10\n\n1721\n",
+            "emergence-pack 1\nday01\t0\n\n",
+        ] {
+            std::fs::write(&path, pack).unwrap();
+            assert_eq!(
+                PackFs::open(&path).unwrap_err().kind(),
+                io::ErrorKind::InvalidData
+            );
+        }
+        std::fs::write(&path, "emergence-pack 1\n\n").unwrap();
+        assert!(PackFs::open(&path).is_ok());
+    }
+
+    #[test]
+    fn cache() {
+        let dir = TempDir::new("emergence").unwrap();
+        let path = dir.path().join("2020.pack");
+        let aoc = AoC::with_fs(2020, "", "token".into(), PackFs::open(&path).unwrap()).unwrap();
+        aoc.set_notes(1, "Part 2 is slow").unwrap();
+
+        let aoc = AoC::with_fs(2020, "", "token".into(), PackFs::open(&path).unwrap()).unwrap();
+        assert_eq!(aoc.notes(1).unwrap().as_deref(), Some("Part 2 is slow"));
+        // Everything is in the pack
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
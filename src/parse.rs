@@ -0,0 +1,222 @@
+//! Helpers for parsing puzzle inputs.
+//!
+//! Most inputs are numbers separated by punctuation or whitespace. [`fast_ints`] and
+//! [`fast_uints`] pull every number out of an input in one pass over its bytes, without
+//! splitting it into lines or words first or going through `str::parse`'s error handling, for the
+//! days where parsing dominates the runtime. See `benches/parse.rs` for a comparison.
+//!
+//! [`infer_shape`] guesses how an input is laid out, for tools that handle every day generically.
+
+/// Every integer in `input`, in order. Anything that isn't a digit separates numbers, and a `-`
+/// directly before a number makes it negative.
+///
+/// Numbers that don't fit in an `i64` wrap around.
+///
+/// ```
+/// # use emergence::parse::fast_ints;
+/// let nums: Vec<_> = fast_ints("p=0,4 v=3,-3\n").collect();
+/// assert_eq!(nums, [0, 4, 3, -3]);
+/// ```
+pub fn fast_ints(input: &str) -> Ints<'_> {
+    Ints {
+        bytes: input.as_bytes(),
+        pos: 0,
+        signed: true,
+    }
+}
+
+/// As [`fast_ints`], but every `-` is treated as a separator, so `1-3` is `1, 3` rather than
+/// `1, -3`. Useful for ranges.
+///
+/// ```
+/// # use emergence::parse::fast_uints;
+/// let nums: Vec<_> = fast_uints("2-4,6-8").collect();
+/// assert_eq!(nums, [2, 4, 6, 8]);
+/// ```
+pub fn fast_uints(input: &str) -> Ints<'_> {
+    Ints {
+        bytes: input.as_bytes(),
+        pos: 0,
+        signed: false,
+    }
+}
+
+/// The iterator returned by [`fast_ints`] and [`fast_uints`]
+#[derive(Debug, Clone)]
+pub struct Ints<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    signed: bool,
+}
+
+impl Iterator for Ints<'_> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        let bytes = self.bytes;
+        let mut i = self.pos;
+        while i < bytes.len() && !bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        if i == bytes.len() {
+            self.pos = i;
+            return None;
+        }
+        let negative = self.signed && i > 0 && bytes[i - 1] == b'-';
+
+        let mut n: i64 = 0;
+        while i < bytes.len() {
+            // Bytes below '0' wrap around to large values, so one comparison checks both bounds
+            let digit = bytes[i].wrapping_sub(b'0');
+            if digit >= 10 {
+                break;
+            }
+            n = n.wrapping_mul(10).wrapping_add(i64::from(digit));
+            i += 1;
+        }
+        self.pos = i;
+        Some(if negative { n.wrapping_neg() } else { n })
+    }
+}
+
+/// The layout of an input, as guessed by [`infer_shape`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Shape {
+    /// A single line that isn't a list of numbers, such as a string of instructions
+    SingleLine { len: usize },
+    /// A rectangle of characters with no whitespace, such as a map
+    Grid { width: usize, height: usize },
+    /// Lines of numbers and punctuation only
+    Ints {
+        lines: usize,
+        /// How many numbers are on each line, if it is the same for every line
+        per_line: Option<usize>,
+    },
+    /// Groups of lines separated by blank lines, each with its own shape
+    Blocks(Vec<Shape>),
+    /// Any other lines of text
+    Lines { count: usize },
+}
+
+/// Guess the layout of `input`. A trailing newline is ignored.
+///
+/// This is a heuristic, and some inputs are ambiguous: for example, lines of digits that are all
+/// the same length are taken to be a grid if they are at least 5 characters long, and a list of
+/// numbers otherwise.
+///
+/// ```
+/// # use emergence::parse::{infer_shape, Shape};
+/// assert_eq!(infer_shape("#..\n.#.\n"), Shape::Grid { width: 3, height: 2 });
+/// assert_eq!(
+///     infer_shape("1,2\n3,4\n"),
+///     Shape::Ints { lines: 2, per_line: Some(2) }
+/// );
+/// ```
+pub fn infer_shape(input: &str) -> Shape {
+    let input = input.strip_suffix('\n').unwrap_or(input);
+    if input.contains("\n\n") {
+        return Shape::Blocks(input.split("\n\n").map(infer_shape).collect());
+    }
+
+    let lines: Vec<_> = input.lines().collect();
+    let width = lines.first().map_or(0, |line| line.len());
+    let uniform = lines.len() > 1
+        && width > 1
+        && lines.iter().all(|line| {
+            line.len() == width && !line.contains(|c: char| c.is_whitespace() || c == ',')
+        });
+    // Short lines of digits are more likely to be a list of numbers that happen to be the same
+    // length than a grid
+    let all_digits = lines
+        .iter()
+        .all(|line| line.bytes().all(|b| b.is_ascii_digit()));
+    if uniform && (width >= 5 || !all_digits) {
+        return Shape::Grid {
+            width,
+            height: lines.len(),
+        };
+    }
+
+    let is_ints = |line: &&str| {
+        line.bytes().any(|b| b.is_ascii_digit()) && !line.bytes().any(|b| b.is_ascii_alphabetic())
+    };
+    if !lines.is_empty() && lines.iter().all(is_ints) {
+        let counts: Vec<_> = lines.iter().map(|line| fast_ints(line).count()).collect();
+        let per_line = counts.iter().all(|&c| c == counts[0]).then_some(counts[0]);
+        return Shape::Ints {
+            lines: lines.len(),
+            per_line,
+        };
+    }
+
+    match lines[..] {
+        [line] => Shape::SingleLine { len: line.len() },
+        _ => Shape::Lines { count: lines.len() },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ints() {
+        let input = "Sensor at x=-2, y=18: beacon at x=-2, y=15\n10 -  20\n-\n007\n";
+        assert_eq!(
+            fast_ints(input).collect::<Vec<_>>(),
+            [-2, 18, -2, 15, 10, 20, 7]
+        );
+        assert_eq!(
+            fast_uints(input).collect::<Vec<_>>(),
+            [2, 18, 2, 15, 10, 20, 7]
+        );
+        assert_eq!(fast_ints("").count(), 0);
+        assert_eq!(fast_ints("no numbers").count(), 0);
+        assert_eq!(fast_ints("9223372036854775807").next(), Some(i64::MAX));
+    }
+
+    #[test]
+    fn shapes() {
+        assert_eq!(
+            infer_shape("1721\n979\n366\n"),
+            Shape::Ints {
+                lines: 3,
+                per_line: Some(1)
+            }
+        );
+        assert_eq!(
+            infer_shape("3,4,3,1,2"),
+            Shape::Ints {
+                lines: 1,
+                per_line: Some(5)
+            }
+        );
+        assert_eq!(
+            infer_shape("30373\n25512\n"),
+            Shape::Grid {
+                width: 5,
+                height: 2
+            }
+        );
+        assert_eq!(infer_shape("R2, L3\n"), Shape::SingleLine { len: 6 });
+        assert_eq!(
+            infer_shape("1-3 a: abcde\n1-3 b: cdefg\n2-9 c: ccccccccc\n"),
+            Shape::Lines { count: 3 }
+        );
+        assert_eq!(
+            infer_shape("1000\n2000\n\n4000\n"),
+            Shape::Blocks(vec![
+                Shape::Ints {
+                    lines: 2,
+                    per_line: Some(1)
+                },
+                Shape::Ints {
+                    lines: 1,
+                    per_line: Some(1)
+                },
+            ])
+        );
+        assert_eq!(infer_shape(""), Shape::Lines { count: 0 });
+    }
+}
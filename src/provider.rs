@@ -0,0 +1,181 @@
+use std::{io, path::PathBuf};
+
+use chrono::{DateTime, FixedOffset};
+
+/// The event name of providers that aren't given one, as they aren't Advent of Code
+const DEFAULT_NAME: &str = "Puzzles";
+
+/// Where puzzle inputs come from and when they are released, so that events run like Advent of
+/// Code can be fetched and cached in the same way. Set with
+/// [`AoC::provider`](crate::AoC::provider); [`AdventOfCode`] is used by default
+pub trait PuzzleProvider: Send + Sync {
+    /// The URL of the input for `day` of `year`
+    fn input_url(&self, year: usize, day: usize) -> String;
+
+    /// The time at which the puzzle for `day` of `year` unlocks, or `None` if there is no such
+    /// puzzle. Days after the 25th are rejected before this is called
+    fn unlock_time(&self, year: usize, day: usize) -> Option<DateTime<FixedOffset>>;
+
+    /// The name of the cookie the session token is sent in, or `None` if the provider isn't run
+    /// by Advent of Code and must not be sent the token at all. In that case no cookies are sent
+    fn token_cookie(&self) -> Option<&str> {
+        Some("session")
+    }
+
+    /// The name of the event, as shown in unlock schedules and notifications
+    fn event_name(&self) -> &str {
+        "Advent of Code"
+    }
+
+    /// Read the input for `day` of `year` without making a request, for providers that don't
+    /// serve inputs over HTTP. Returns `None` to fetch it from [`PuzzleProvider::input_url`] as
+    /// usual, which is the default
+    fn read_input(&self, year: usize, day: usize) -> Option<io::Result<String>> {
+        let _ = (year, day);
+        None
+    }
+}
+
+/// The default [`PuzzleProvider`]: inputs from `adventofcode.com`, unlocking at midnight EST on
+/// each of the first 25 days of December
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AdventOfCode;
+
+impl PuzzleProvider for AdventOfCode {
+    fn input_url(&self, year: usize, day: usize) -> String {
+        format!("https://adventofcode.com/{year}/day/{day}/input")
+    }
+
+    fn unlock_time(&self, year: usize, day: usize) -> Option<DateTime<FixedOffset>> {
+        crate::unlock_time(year.try_into().ok()?, day.try_into().ok()?)
+    }
+}
+
+/// A [`PuzzleProvider`] serving inputs from a directory laid out like the cache:
+/// `{year}/day01.txt`, `{year}/day02.txt` and so on. Every day is released immediately
+///
+/// This lets custom inputs, such as practice sets handed out by a teacher, be read through the
+/// same interface as real ones.
+#[derive(Debug, Clone)]
+pub struct LocalDirectory {
+    path: PathBuf,
+    name: String,
+}
+
+impl LocalDirectory {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            name: DEFAULT_NAME.to_owned(),
+        }
+    }
+
+    /// Set the name of the event, see [`PuzzleProvider::event_name`]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    fn input_path(&self, year: usize, day: usize) -> PathBuf {
+        self.path
+            .join(year.to_string())
+            .join(format!("day{day:02}.txt"))
+    }
+}
+
+impl PuzzleProvider for LocalDirectory {
+    /// A `file://` URL, only used in messages as inputs are read directly
+    fn input_url(&self, year: usize, day: usize) -> String {
+        format!("file://{}", self.input_path(year, day).display())
+    }
+
+    fn unlock_time(&self, _year: usize, _day: usize) -> Option<DateTime<FixedOffset>> {
+        Some(DateTime::UNIX_EPOCH.fixed_offset())
+    }
+
+    fn event_name(&self) -> &str {
+        &self.name
+    }
+
+    fn read_input(&self, year: usize, day: usize) -> Option<io::Result<String>> {
+        Some(std::fs::read_to_string(self.input_path(year, day)))
+    }
+}
+
+/// A [`PuzzleProvider`] fetching inputs from a URL made by replacing `{year}` and `{day}` in a
+/// template, e.g. `https://example.com/practice/{year}/{day}.txt`. Every day is released
+/// immediately
+///
+/// The server is not trusted with the Advent of Code session token, so requests to it carry no
+/// cookies.
+#[derive(Debug, Clone)]
+pub struct UrlTemplate {
+    template: String,
+    name: String,
+}
+
+impl UrlTemplate {
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            name: DEFAULT_NAME.to_owned(),
+        }
+    }
+
+    /// Set the name of the event, see [`PuzzleProvider::event_name`]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+}
+
+impl PuzzleProvider for UrlTemplate {
+    fn input_url(&self, year: usize, day: usize) -> String {
+        self.template
+            .replace("{year}", &year.to_string())
+            .replace("{day}", &day.to_string())
+    }
+
+    fn unlock_time(&self, _year: usize, _day: usize) -> Option<DateTime<FixedOffset>> {
+        Some(DateTime::UNIX_EPOCH.fixed_offset())
+    }
+
+    fn token_cookie(&self) -> Option<&str> {
+        None
+    }
+
+    fn event_name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_urls() {
+        assert_eq!(
+            AdventOfCode.input_url(2020, 1),
+            "https://adventofcode.com/2020/day/1/input"
+        );
+        assert_eq!(
+            UrlTemplate::new("https://example.com/{year}/{day}.txt").input_url(2020, 7),
+            "https://example.com/2020/7.txt"
+        );
+        assert_eq!(
+            LocalDirectory::new("/inputs").input_url(2020, 7),
+            "file:///inputs/2020/day07.txt"
+        );
+    }
+
+    #[test]
+    fn event_names() {
+        assert_eq!(AdventOfCode.event_name(), "Advent of Code");
+        assert_eq!(UrlTemplate::new("").event_name(), "Puzzles");
+        assert_eq!(
+            LocalDirectory::new("/inputs").name("Practice").event_name(),
+            "Practice"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! A Markdown progress table for READMEs, kept up to date between marker comments.
+
+use std::{fmt::Write, path::Path};
+
+use crate::{Calendar, Error, Stars};
+
+/// Marks the start of the section of a README that [`Calendar::update_readme`] rewrites
+pub const README_START: &str = "<!-- emergence:progress:start -->";
+/// Marks the end of the section of a README that [`Calendar::update_readme`] rewrites
+pub const README_END: &str = "<!-- emergence:progress:end -->";
+
+impl Calendar {
+    /// Render a Markdown table of each released day, with a link to its puzzle and its stars
+    pub fn progress_table(&self) -> String {
+        let mut table = String::from("| Day | Puzzle | Stars |\n| ---: | --- | :---: |\n");
+        for day in 1..=self.days.len() {
+            let stars = match self.stars(day) {
+                Stars::Two => "⭐⭐",
+                Stars::One => "⭐",
+                Stars::None => "",
+            };
+            // Writing to a `String` can't fail
+            let _ = writeln!(
+                table,
+                "| {day} | [Day {day}](https://adventofcode.com/{}/day/{day}) | {stars} |",
+                self.year
+            );
+        }
+        let _ = writeln!(
+            table,
+            "\n{} of {} stars",
+            self.total_stars(),
+            2 * self.days.len()
+        );
+        table
+    }
+
+    /// Replace the section of the README at `path` between [`README_START`] and [`README_END`]
+    /// with [`Calendar::progress_table`], leaving the rest untouched. Returns whether the file
+    /// changed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::MissingReadmeMarkers`] if the markers are not both present, in order.
+    pub fn update_readme(&self, path: impl AsRef<Path>) -> Result<bool, Error> {
+        let path = path.as_ref();
+        let readme = std::fs::read_to_string(path)?;
+        let updated = replace_section(&readme, &self.progress_table())
+            .ok_or_else(|| Error::MissingReadmeMarkers(path.to_owned()))?;
+        if updated == readme {
+            return Ok(false);
+        }
+        std::fs::write(path, updated)?;
+        Ok(true)
+    }
+}
+
+/// Replace everything between the markers in `readme` with `section`, or `None` if the markers
+/// are missing
+fn replace_section(readme: &str, section: &str) -> Option<String> {
+    let start = readme.find(README_START)? + README_START.len();
+    let end = start + readme[start..].find(README_END)?;
+    Some(format!("{}\n{section}{}", &readme[..start], &readme[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_table() {
+        let calendar = Calendar {
+            year: 2020,
+            days: vec![Stars::Two, Stars::One, Stars::None],
+            art: String::new(),
+        };
+        let table = calendar.progress_table();
+        assert!(table.contains("| 1 | [Day 1](https://adventofcode.com/2020/day/1) | ⭐⭐ |\n"));
+        assert!(table.contains("| 3 | [Day 3](https://adventofcode.com/2020/day/3) |  |\n"));
+        assert!(table.ends_with("\n3 of 6 stars\n"));
+    }
+
+    #[test]
+    fn replace_section() {
+        let readme = format!("# Solutions\n{README_START}\nold\n{README_END}\nMore\n");
+        assert_eq!(
+            super::replace_section(&readme, "new\n").unwrap(),
+            format!("# Solutions\n{README_START}\nnew\n{README_END}\nMore\n")
+        );
+        assert_eq!(super::replace_section("# Solutions\n", "new\n"), None);
+        assert_eq!(
+            super::replace_section(&format!("{README_END}\n{README_START}\n"), "new\n"),
+            None
+        );
+    }
+}
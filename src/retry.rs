@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use crate::Error;
+
+/// How failed requests are retried, set with [`AoC::retry_policy`](crate::AoC::retry_policy)
+///
+/// Retries back off exponentially, doubling the delay after each attempt up to `max_delay`.
+/// Throttling responses are never retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The total number of attempts to make, including the first. `1` disables retrying
+    pub max_attempts: u32,
+    /// The delay before the first retry
+    pub base_delay: Duration,
+    /// The longest delay between two attempts
+    pub max_delay: Duration,
+    /// Response statuses that are worth retrying
+    pub retry_statuses: Vec<StatusCode>,
+    /// Whether to retry requests that failed to connect, timed out, or were cut off
+    pub retry_connection_errors: bool,
+}
+
+impl RetryPolicy {
+    /// Never retry. This is the default for [`AoC`](crate::AoC)
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// The delay after the given (1-indexed) failed attempt
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay)
+    }
+
+    pub(crate) fn is_retryable(&self, e: &Error) -> bool {
+        match e {
+            Error::Status { status, .. } => self.retry_statuses.contains(status),
+            Error::Reqwest(e) => self.retry_connection_errors && (e.is_connect() || e.is_timeout()),
+            Error::Incomplete { .. } => self.retry_connection_errors,
+            _ => false,
+        }
+    }
+}
+
+/// Three attempts, waiting one then two seconds, on server errors and connection failures
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            retry_statuses: vec![
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_connection_errors: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay() {
+        let policy = RetryPolicy {
+            max_delay: Duration::from_secs(5),
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(4), Duration::from_secs(5));
+        assert_eq!(policy.delay(100), Duration::from_secs(5));
+    }
+}
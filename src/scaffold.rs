@@ -0,0 +1,182 @@
+//! Generating a new Advent of Code solutions crate, wired up to emergence.
+//!
+//! The generated crate has a module per day, each with a `part1` and `part2` function, and a
+//! `main` that fetches the input for the day given on the command line and runs and times both
+//! parts.
+
+use std::{
+    fmt::Write,
+    fs::OpenOptions,
+    io::{self, Write as _},
+    path::Path,
+};
+
+use crate::Error;
+
+/// Generate a solutions crate called `name` for `year` in the directory `path`, which is created
+/// if necessary
+///
+/// Existing files are never overwritten: if any of the files to be generated already exists,
+/// this fails with an [`std::io::ErrorKind::AlreadyExists`] error before anything is written.
+pub fn generate(path: impl AsRef<Path>, name: &str, year: usize) -> Result<(), Error> {
+    let path = path.as_ref();
+    let mut files = vec![
+        (path.join("Cargo.toml"), cargo_toml(name)),
+        (path.join(".gitignore"), GITIGNORE.to_owned()),
+        (path.join("src/main.rs"), main_rs(year)),
+    ];
+    files.extend((1..=25).map(|day| (path.join(format!("src/day{day:02}.rs")), DAY_RS.to_owned())));
+
+    if let Some((existing, _)) = files
+        .iter()
+        .find(|(file, _)| file.symlink_metadata().is_ok())
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", existing.display()),
+        )
+        .into());
+    }
+    std::fs::create_dir_all(path.join("src"))?;
+    for (file, contents) in &files {
+        create(file, contents)?;
+    }
+    Ok(())
+}
+
+/// Write `contents` to a new file at `path`, failing if it already exists
+fn create(path: &Path, contents: &str) -> io::Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?
+        .write_all(contents.as_bytes())
+}
+
+fn cargo_toml(name: &str) -> String {
+    format!(
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+emergence = "{}"
+"#,
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+const GITIGNORE: &str = "\
+/target
+# Inputs are cached by emergence in ~/.aoc, and must not be committed
+/inputs/
+*.input
+tokenfile
+";
+
+fn main_rs(year: usize) -> String {
+    let mut mods = String::new();
+    let mut arms = String::new();
+    for day in 1..=25 {
+        // Writing to a `String` can't fail
+        let _ = writeln!(mods, "mod day{day:02};");
+        let _ = writeln!(
+            arms,
+            "        {day} => run(&input, day{day:02}::part1, day{day:02}::part2),"
+        );
+    }
+
+    format!(
+        r#"use std::{{error::Error, fmt::Display, time::Instant}};
+
+use emergence::AoC;
+
+{mods}
+fn run<A: Display, B: Display>(input: &str, part1: fn(&str) -> A, part2: fn(&str) -> B) {{
+    let start = Instant::now();
+    let answer = part1(input);
+    println!("Part 1: {{answer}} ({{:?}})", start.elapsed());
+
+    let start = Instant::now();
+    let answer = part2(input);
+    println!("Part 2: {{answer}} ({{:?}})", start.elapsed());
+}}
+
+fn main() -> Result<(), Box<dyn Error>> {{
+    let day: usize = std::env::args()
+        .nth(1)
+        .ok_or("usage: cargo run -- <day>")?
+        .parse()?;
+
+    let aoc = AoC::new({year})?;
+    let input = aoc.read_or_fetch(day)?;
+
+    match day {{
+{arms}        _ => return Err("Advent of Code only has 25 days".into()),
+    }}
+    Ok(())
+}}
+"#
+    )
+}
+
+const DAY_RS: &str = "\
+pub fn part1(input: &str) -> usize {
+    let _ = input;
+    todo!()
+}
+
+pub fn part2(input: &str) -> usize {
+    let _ = input;
+    todo!()
+}
+";
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn generates() {
+        let dir = TempDir::new("emergence").unwrap();
+        generate(dir.path(), "aoc2020", 2020).unwrap();
+
+        let main = std::fs::read_to_string(dir.path().join("src/main.rs")).unwrap();
+        assert!(main.contains("AoC::new(2020)"));
+        assert!(main.contains("mod day25;"));
+        assert!(main.contains("7 => run(&input, day07::part1, day07::part2),"));
+        assert!(dir.path().join("src/day01.rs").is_file());
+        let cargo = std::fs::read_to_string(dir.path().join("Cargo.toml")).unwrap();
+        assert!(cargo.contains("name = \"aoc2020\""));
+
+        assert!(matches!(
+            generate(dir.path(), "aoc2020", 2020),
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::AlreadyExists
+        ));
+    }
+
+    #[test]
+    fn existing_file() {
+        let dir = TempDir::new("emergence").unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/day25.rs"), "mine").unwrap();
+
+        assert!(matches!(
+            generate(dir.path(), "aoc2020", 2020),
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::AlreadyExists
+        ));
+        // Nothing else was written
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert_eq!(
+            std::fs::read_dir(dir.path().join("src")).unwrap().count(),
+            1
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("src/day25.rs")).unwrap(),
+            "mine"
+        );
+    }
+}
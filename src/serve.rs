@@ -0,0 +1,141 @@
+//! A minimal HTTP server sharing inputs with other machines, see
+//! [`AoC::serve`](crate::AoC::serve).
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::Error;
+
+/// The longest request line and headers that are read, so a client can't use unbounded memory
+const MAX_HEAD: u64 = 8192;
+
+/// How long a client may take to send its request, or to read the response, before it is
+/// disconnected, so idle clients don't keep their threads forever
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serve inputs from `fetch` to every connection to `listener`, each on its own thread
+pub(crate) fn serve<F>(listener: TcpListener, year: usize, fetch: F) -> io::Result<()>
+where
+    F: Fn(usize) -> Result<String, Error> + Send + Sync + 'static,
+{
+    let fetch = Arc::new(fetch);
+    for stream in listener.incoming() {
+        // Failing to accept one connection, e.g. because too many files are open, shouldn't stop
+        // the server. The pause stops a persistent error from spinning
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("Could not accept a connection: {e}");
+                std::thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+        };
+        let fetch = fetch.clone();
+        std::thread::spawn(move || handle(stream, year, &*fetch));
+    }
+    Ok(())
+}
+
+/// Respond to a single request
+fn handle(stream: TcpStream, year: usize, fetch: &dyn Fn(usize) -> Result<String, Error>) {
+    if stream.set_read_timeout(Some(TIMEOUT)).is_err()
+        || stream.set_write_timeout(Some(TIMEOUT)).is_err()
+    {
+        return;
+    }
+    let mut reader = BufReader::new((&stream).take(MAX_HEAD));
+    let mut request_line = String::new();
+    // The headers are ignored, but must be read before responding
+    let mut line = String::new();
+    let complete = reader.read_line(&mut request_line).is_ok_and(|n| n > 0)
+        && loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => break false,
+                Ok(_) if line.trim_end().is_empty() => break true,
+                Ok(_) => {}
+            }
+        };
+
+    let mut words = request_line.split_whitespace();
+    let (method, path) = (
+        words.next().unwrap_or_default(),
+        words.next().unwrap_or_default(),
+    );
+    let (status, body) = if !complete {
+        ("400 Bad Request", String::new())
+    } else if method != "GET" && method != "HEAD" {
+        ("405 Method Not Allowed", String::new())
+    } else {
+        match parse_path(path, year) {
+            None => ("404 Not Found", String::new()),
+            Some(day) => match fetch(day) {
+                Ok(input) => ("200 OK", input),
+                Err(
+                    e @ (Error::PuzzleNotFound { .. }
+                    | Error::NotYetReleased(_)
+                    | Error::DayZero
+                    | Error::OutOfBounds),
+                ) => ("404 Not Found", format!("{e}\n")),
+                Err(e @ Error::Throttled) => ("429 Too Many Requests", format!("{e}\n")),
+                Err(e) => ("502 Bad Gateway", format!("{e}\n")),
+            },
+        }
+    };
+
+    // A client hanging up early isn't the server's problem
+    let mut stream = &stream;
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    if method != "HEAD" {
+        let _ = stream.write_all(body.as_bytes());
+    }
+}
+
+/// The day from a path like Advent of Code's, `/{year}/day/{day}/input`
+fn parse_path(path: &str, year: usize) -> Option<usize> {
+    let rest = path.strip_prefix(&format!("/{year}/day/"))?;
+    rest.strip_suffix("/input")?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || serve(listener, 2020, |_| Ok("input\n".to_owned())));
+
+        // Exactly as much as is read, so the headers never end
+        let request_line = "GET /2020/day/1/input HTTP/1.1\r\n";
+        let padding = "a".repeat(MAX_HEAD as usize - request_line.len() - "X-Padding: ".len());
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "{request_line}X-Padding: {padding}").unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET /2020/day/1/input HTTP/1.1\r\n\r\n").unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        assert!(res.ends_with("\r\n\r\ninput\n"));
+    }
+
+    #[test]
+    fn parse_path() {
+        assert_eq!(super::parse_path("/2020/day/7/input", 2020), Some(7));
+        assert_eq!(super::parse_path("/2021/day/7/input", 2020), None);
+        assert_eq!(super::parse_path("/2020/day/7", 2020), None);
+        assert_eq!(super::parse_path("/2020/day/x/input", 2020), None);
+    }
+}
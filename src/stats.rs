@@ -0,0 +1,95 @@
+use crate::html;
+
+/// How many users have completed a day's puzzle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DayStats {
+    pub day: usize,
+    /// Users with both stars
+    pub both_stars: u64,
+    /// Users with only the first star
+    pub first_star_only: u64,
+}
+
+impl DayStats {
+    /// Users with at least the first star
+    pub fn first_star(&self) -> u64 {
+        self.both_stars + self.first_star_only
+    }
+}
+
+/// A year's completion statistics, as returned by [`AoC::stats`](crate::AoC::stats)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+    pub year: usize,
+    /// Statistics for each released day, in order. `days[0]` is day 1
+    pub days: Vec<DayStats>,
+}
+
+impl Stats {
+    /// Parse the statistics from the HTML of `https://adventofcode.com/{year}/stats`
+    pub(crate) fn parse(year: usize, page: &str) -> Self {
+        let body = html::element(page, "<pre class=\"stats", "</pre>").unwrap_or_default();
+        let mut days: Vec<_> = body.split("<a ").skip(1).filter_map(parse_day).collect();
+        days.sort_by_key(|d| d.day);
+        Self { year, days }
+    }
+
+    /// The statistics for `day`, if it has been released
+    pub fn day(&self, day: usize) -> Option<&DayStats> {
+        self.days.iter().find(|d| d.day == day)
+    }
+}
+
+/// Parse a single day's line, e.g. `href="/2020/day/1"> 1 <span class="stats-both"> 1234</span>
+/// <span class="stats-firstonly">  56</span>  <span class="stats-both">**</span>...`
+fn parse_day(line: &str) -> Option<DayStats> {
+    let day = html::attr(line, "href")?.rsplit('/').next()?.parse().ok()?;
+    // The counts come first; the spans with the same classes after them are the bar chart
+    let count = |class| {
+        html::text(html::element(line, class, "</span>")?)
+            .trim()
+            .parse()
+            .ok()
+    };
+    Some(DayStats {
+        day,
+        both_stars: count("<span class=\"stats-both\"")?,
+        first_star_only: count("<span class=\"stats-firstonly\"")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<main>
+<article><p>The first column shows the number of users who have completed both parts.</p></article>
+<pre class="stats"><a href="/2020/day/2"> 2  <span class="stats-both"> 15000</span> <span class="stats-firstonly">  1200</span>  <span class="stats-both">*********</span><span class="stats-firstonly">*</span></a>
+<a href="/2020/day/1"> 1  <span class="stats-both"> 20000</span> <span class="stats-firstonly">   800</span>  <span class="stats-both">************</span><span class="stats-firstonly"></span></a>
+</pre>
+</main>"#;
+
+    #[test]
+    fn parse() {
+        let stats = Stats::parse(2020, PAGE);
+        assert_eq!(
+            stats.days,
+            [
+                DayStats {
+                    day: 1,
+                    both_stars: 20000,
+                    first_star_only: 800,
+                },
+                DayStats {
+                    day: 2,
+                    both_stars: 15000,
+                    first_star_only: 1200,
+                },
+            ]
+        );
+        assert_eq!(stats.day(2).unwrap().first_star(), 16200);
+        assert!(stats.day(3).is_none());
+    }
+}
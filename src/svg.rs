@@ -0,0 +1,94 @@
+//! SVG images of a year's progress, for embedding in READMEs.
+
+use std::fmt::Write;
+
+use crate::{Calendar, Stars};
+
+const GOLD: &str = "#ffff66";
+const SILVER: &str = "#9999cc";
+const GREY: &str = "#333340";
+const BACKGROUND: &str = "#0f0f23";
+
+impl Calendar {
+    /// Render a shields.io-style badge reading e.g. `AoC 2023 | 42★`
+    pub fn badge_svg(&self) -> String {
+        let label = format!("AoC {}", self.year);
+        let value = format!("{}★", self.total_stars());
+        // Roughly the width of 11px Verdana, which is what badges conventionally use
+        let label_width = 10 + 7 * label.chars().count();
+        let value_width = 10 + 7 * value.chars().count();
+        let width = label_width + value_width;
+
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {value}">
+<rect width="{label_width}" height="20" fill="#555"/>
+<rect x="{label_width}" width="{value_width}" height="20" fill="{BACKGROUND}"/>
+<g fill="#fff" font-family="Verdana,DejaVu Sans,sans-serif" font-size="11" text-anchor="middle">
+<text x="{label_x}" y="14">{label}</text>
+<text x="{value_x}" y="14" fill="{GOLD}">{value}</text>
+</g>
+</svg>
+"##,
+            label_x = label_width / 2,
+            value_x = label_width + value_width / 2,
+        )
+    }
+
+    /// Render a grid of the 25 days, coloured gold for two stars, silver for one, and grey for
+    /// none
+    pub fn calendar_svg(&self) -> String {
+        const CELL: usize = 24;
+        const GAP: usize = 4;
+        const COLUMNS: usize = 5;
+        let size = COLUMNS * (CELL + GAP) + GAP;
+
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" role="img" aria-label="Advent of Code {} progress">
+<rect width="{size}" height="{size}" fill="{BACKGROUND}"/>
+<g font-family="monospace" font-size="11" text-anchor="middle">
+"#,
+            self.year
+        );
+        for day in 1..=25 {
+            let (row, col) = ((day - 1) / COLUMNS, (day - 1) % COLUMNS);
+            let (x, y) = (GAP + col * (CELL + GAP), GAP + row * (CELL + GAP));
+            let (fill, text, title) = match self.stars(day) {
+                Stars::Two => (GOLD, BACKGROUND, "two stars"),
+                Stars::One => (SILVER, BACKGROUND, "one star"),
+                Stars::None => (GREY, "#cccccc", "no stars"),
+            };
+            // Writing to a `String` can't fail
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{x}" y="{y}" width="{CELL}" height="{CELL}" fill="{fill}"><title>Day {day}, {title}</title></rect><text x="{}" y="{}" fill="{text}">{day}</text>"#,
+                x + CELL / 2,
+                y + CELL / 2 + 4,
+            );
+        }
+        svg.push_str("</g>\n</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let calendar = Calendar {
+            year: 2023,
+            days: vec![Stars::Two, Stars::One],
+            art: String::new(),
+        };
+        let badge = calendar.badge_svg();
+        assert!(badge.contains(">AoC 2023</text>"));
+        assert!(badge.contains(">3★</text>"));
+
+        let svg = calendar.calendar_svg();
+        assert_eq!(svg.matches("<rect x=").count(), 25);
+        assert!(svg.contains(&format!(r#"fill="{GOLD}"><title>Day 1, two stars"#)));
+        assert!(svg.contains(&format!(r#"fill="{SILVER}"><title>Day 2, one star"#)));
+        assert!(svg.contains(&format!(r#"fill="{GREY}"><title>Day 25, no stars"#)));
+    }
+}
@@ -0,0 +1,146 @@
+//! Helpers for testing code that uses emergence, enabled with the `test-util` feature.
+//!
+//! [`MockAoC`] is an [`AoC`] that never touches the network or the disk, so tests and doctests
+//! need no token, tempdir or internet connection:
+//!
+//! ```
+//! # use emergence::test_util::MockAoC;
+//! let aoc = MockAoC::new(2020).input(1, "1721\n979\n366\n299\n675\n1456\n");
+//! assert_eq!(aoc.read_or_fetch(1)?.lines().count(), 6);
+//! assert_eq!(aoc.read_or_fetch(2)?, MockAoC::fixture(2020, 2));
+//! # Ok::<(), emergence::Error>(())
+//! ```
+
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Utc;
+use reqwest::blocking::Request;
+
+use crate::{AoC, MemoryFs, Next, unlock_time};
+
+/// An [`AoC`] backed by an in-memory cache, which serves fixture inputs instead of making
+/// requests.
+///
+/// Every released day has an input: either one given with [`MockAoC::input`], or a small
+/// generated one (see [`MockAoC::fixture`]). Requests for the calendar get a calendar page with
+/// no stars. Any other request fails with a 404.
+pub struct MockAoC {
+    aoc: AoC,
+    inputs: Arc<Mutex<HashMap<usize, String>>>,
+}
+
+impl MockAoC {
+    /// Mock the given year's event
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no event for `year`, as [`AoC::with_path_and_token`] would fail
+    pub fn new(year: usize) -> Self {
+        let inputs = Arc::new(Mutex::new(HashMap::new()));
+        let aoc = AoC::with_fs(year, "mock", "mock".into(), MemoryFs::new())
+            .expect("invalid year for MockAoC")
+            .middleware({
+                let inputs = Arc::clone(&inputs);
+                move |req: Request, _: Next<'_>| Ok(respond(year, &inputs, &req))
+            });
+        Self { aoc, inputs }
+    }
+
+    /// Serve `input` for the specified day instead of the generated fixture
+    pub fn input(self, day: usize, input: impl Into<String>) -> Self {
+        self.inputs.lock().unwrap().insert(day, input.into());
+        self
+    }
+
+    /// The generated input served for a day that hasn't been given one: ten lines of numbers,
+    /// which are always the same for the same year and day
+    pub fn fixture(year: usize, day: usize) -> String {
+        let mut state = (year * 100 + day) as u64;
+        (0..10)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                format!("{}\n", (state >> 33) % 10_000)
+            })
+            .collect()
+    }
+
+    /// The underlying [`AoC`], for configuring it further
+    pub fn into_inner(self) -> AoC {
+        self.aoc
+    }
+}
+
+impl Deref for MockAoC {
+    type Target = AoC;
+
+    fn deref(&self) -> &AoC {
+        &self.aoc
+    }
+}
+
+/// The mock response to `req`
+fn respond(
+    year: usize,
+    inputs: &Mutex<HashMap<usize, String>>,
+    req: &Request,
+) -> reqwest::blocking::Response {
+    let path = req.url().path();
+    let input_day = path
+        .strip_prefix(&format!("/{year}/day/"))
+        .and_then(|rest| rest.strip_suffix("/input"))
+        .and_then(|day| day.parse::<usize>().ok());
+
+    let body = if let Some(day) = input_day {
+        inputs
+            .lock()
+            .unwrap()
+            .get(&day)
+            .cloned()
+            .unwrap_or_else(|| MockAoC::fixture(year, day))
+    } else if path == format!("/{year}") {
+        calendar(year)
+    } else {
+        return http::Response::builder()
+            .status(404)
+            .body(String::new())
+            .unwrap()
+            .into();
+    };
+    http::Response::new(body).into()
+}
+
+/// A calendar page with every released day linked, and no stars
+fn calendar(year: usize) -> String {
+    let days: String = (1..=25)
+        .take_while(|&day| unlock_time(year as i32, day).is_some_and(|t| t <= Utc::now()))
+        .map(|day| format!("<a aria-label=\"Day {day}\" href=\"/{year}/day/{day}\">{day}</a>\n"))
+        .collect();
+    format!("<main>\n<pre class=\"calendar\">\n{days}</pre>\n</main>\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, Stars};
+
+    #[test]
+    fn mock() {
+        let aoc = MockAoC::new(2020).input(3, "custom");
+        assert_eq!(aoc.read_or_fetch(1).unwrap(), MockAoC::fixture(2020, 1));
+        assert_ne!(MockAoC::fixture(2020, 1), MockAoC::fixture(2020, 2));
+        assert_eq!(aoc.read_or_fetch(3).unwrap(), "custom");
+
+        let calendar = aoc.calendar().unwrap();
+        assert_eq!(calendar.days, [Stars::None; 25]);
+        assert!(matches!(
+            aoc.global_leaderboard(1),
+            Err(Error::PuzzleNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Where to look for a tokenfile, set with
+/// [`AoC::with_token_search`](crate::AoC::with_token_search)
+///
+/// The search starts in the current directory and moves up through its parents. By default it
+/// stops at the root of the enclosing git repository or at the home directory, whichever comes
+/// first, so that a tokenfile belonging to someone else higher up on a shared machine is never
+/// used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenSearch {
+    /// The names a tokenfile may have, in order of preference. Defaults to just `tokenfile`
+    pub names: Vec<String>,
+    /// Don't search above the first directory containing `.git`
+    pub stop_at_git_root: bool,
+    /// Don't search above the current user's home directory
+    pub stop_at_home: bool,
+}
+
+impl Default for TokenSearch {
+    fn default() -> Self {
+        Self {
+            names: vec!["tokenfile".to_owned()],
+            stop_at_git_root: true,
+            stop_at_home: true,
+        }
+    }
+}
+
+impl TokenSearch {
+    /// Find a file with one of `names`, starting in the current directory
+    pub(crate) fn find(&self, names: &[impl AsRef<str>]) -> io::Result<Option<PathBuf>> {
+        self.find_from(std::env::current_dir()?, names, dirs::home_dir().as_deref())
+    }
+
+    fn find_from(
+        &self,
+        mut dir: PathBuf,
+        names: &[impl AsRef<str>],
+        home: Option<&Path>,
+    ) -> io::Result<Option<PathBuf>> {
+        loop {
+            for name in names {
+                let candidate = dir.join(name.as_ref());
+                if candidate.is_file() {
+                    return Ok(Some(candidate));
+                }
+            }
+            if (self.stop_at_git_root && dir.join(".git").exists())
+                || (self.stop_at_home && home == Some(&*dir))
+                || !dir.pop()
+            {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// Whether the file at `path` can be read by users other than its owner. Always false on
+/// platforms without Unix permissions
+pub(crate) fn is_shared(path: &Path) -> io::Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Ok(std::fs::metadata(path)?.permissions().mode() & 0o044 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(false)
+    }
+}
+
+/// Write `token` to the file at `path`, creating it if needed, and make it readable and
+/// writable only by its owner
+pub(crate) fn create(path: &Path, token: &str) -> io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        // The mode only applies to new files, so existing ones are restricted separately. This is
+        // done before writing, so the token is never readable by others
+        options.mode(0o600);
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    io::Write::write_all(&mut options.open(path)?, format!("{token}\n").as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn boundaries() {
+        let dir = TempDir::new("emergence").unwrap();
+        let root = dir.path();
+        let repo = root.join("home/repo");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        std::fs::write(root.join("tokenfile"), "theirs").unwrap();
+        std::fs::write(root.join("home/session.txt"), "mine").unwrap();
+
+        let names = ["tokenfile", "session.txt"];
+        let home = Some(root.join("home"));
+        let find = |search: &TokenSearch| {
+            search
+                .find_from(repo.join("src"), &names, home.as_deref())
+                .unwrap()
+        };
+        assert_eq!(find(&TokenSearch::default()), None);
+
+        let search = TokenSearch {
+            stop_at_git_root: false,
+            ..Default::default()
+        };
+        assert_eq!(find(&search), Some(root.join("home/session.txt")));
+
+        let search = TokenSearch {
+            stop_at_git_root: false,
+            stop_at_home: false,
+            ..Default::default()
+        };
+        // The closest match wins, regardless of the order of names
+        assert_eq!(find(&search), Some(root.join("home/session.txt")));
+        std::fs::remove_file(root.join("home/session.txt")).unwrap();
+        assert_eq!(find(&search), Some(root.join("tokenfile")));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("emergence").unwrap();
+        let path = dir.path().join("tokenfile");
+        std::fs::write(&path, "old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(is_shared(&path).unwrap());
+
+        create(&path, "TOKEN").unwrap();
+        assert!(!is_shared(&path).unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "TOKEN\n");
+
+        let new = dir.path().join("new");
+        create(&new, "TOKEN").unwrap();
+        assert_eq!(
+            std::fs::metadata(&new).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+}
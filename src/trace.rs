@@ -0,0 +1,125 @@
+use std::{fs::File, io::Write, path::Path, sync::Mutex, time::Instant};
+
+use reqwest::{
+    blocking::{Request, Response},
+    header::{COOKIE, HeaderMap, SET_COOKIE},
+};
+
+use crate::{Error, Middleware, Next, middleware::Buffered};
+
+/// The default number of bytes of each response body to write to the trace
+const DEFAULT_MAX_BODY: usize = 1024;
+
+/// Middleware that appends a trace of every request and response (headers, status, timing and the
+/// start of the body) to a file, for diagnosing failing fetches.
+///
+/// Cookie values, including the session token, are redacted, so traces are safe to attach to bug
+/// reports. Add this after any other middleware to trace what is actually sent:
+///
+/// ```no_run
+/// # use emergence::{AoC, Trace};
+/// let aoc = AoC::new(2022)?.middleware(Trace::new("emergence.log")?);
+/// # Ok::<(), emergence::Error>(())
+/// ```
+pub struct Trace {
+    file: Mutex<File>,
+    max_body: usize,
+}
+
+impl Trace {
+    /// Trace to the file at `path`, appending if it already exists
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+            max_body: DEFAULT_MAX_BODY,
+        })
+    }
+
+    /// Set how many bytes of each response body are written. Defaults to 1024
+    pub fn max_body(mut self, max_body: usize) -> Self {
+        self.max_body = max_body;
+        self
+    }
+}
+
+impl Middleware for Trace {
+    fn handle(&self, req: Request, next: Next<'_>) -> Result<Response, Error> {
+        let mut trace = format!("> {} {}\n", req.method(), req.url());
+        write_headers(&mut trace, '>', req.headers());
+
+        let start = Instant::now();
+        let res = next.run(req).and_then(|res| {
+            let res = Buffered::read(res)?;
+
+            trace += &format!("< {} ({:?})\n", res.status, start.elapsed());
+            write_headers(&mut trace, '<', &res.headers);
+            let shown = &res.body[..res.body.len().min(self.max_body)];
+            trace += &String::from_utf8_lossy(shown);
+            if shown.len() < res.body.len() {
+                trace += &format!("\n[{} more bytes]", res.body.len() - shown.len());
+            }
+            trace.push('\n');
+
+            Ok(res.into_response())
+        });
+        if let Err(e) = &res {
+            trace += &format!("! {e} ({:?})\n", start.elapsed());
+        }
+        trace.push('\n');
+
+        // A trace that can't be written shouldn't stop the request from succeeding
+        let _ = self.file.lock().unwrap().write_all(trace.as_bytes());
+        res
+    }
+}
+
+/// Write `headers` to `trace`, one per line, with cookie values redacted
+fn write_headers(trace: &mut String, prefix: char, headers: &HeaderMap) {
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        let value = if name == COOKIE || name == SET_COOKIE {
+            redact_cookies(&value)
+        } else {
+            value.into_owned()
+        };
+        *trace += &format!("{prefix} {name}: {value}\n");
+    }
+}
+
+/// Replace the value of every cookie in a `Cookie` or `Set-Cookie` header, keeping the names and
+/// any attributes
+fn redact_cookies(header: &str) -> String {
+    header
+        .split(';')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if !is_attribute(name) => format!("{name}=<redacted>"),
+            _ => pair.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Whether `name` is a `Set-Cookie` attribute rather than a cookie
+fn is_attribute(name: &str) -> bool {
+    ["expires", "max-age", "domain", "path", "samesite"]
+        .iter()
+        .any(|attr| name.trim().eq_ignore_ascii_case(attr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redaction() {
+        assert_eq!(
+            redact_cookies("session=53616c7465645f5f; ru=abc"),
+            "session=<redacted>; ru=<redacted>"
+        );
+        assert_eq!(
+            redact_cookies("session=abc; Path=/; Max-Age=3600; Secure"),
+            "session=<redacted>; Path=/; Max-Age=3600; Secure"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt;
+
+/// Something wrong with an input, found by [`AoC::validate_cached`](crate::AoC::validate_cached)
+/// or when fetching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputProblem {
+    /// The input is empty, or only whitespace
+    Empty,
+    /// The input is an HTML page, such as an error page or a login form
+    Html,
+    /// The input doesn't end with a newline, as every Advent of Code input does, so may have
+    /// been cut off
+    Unterminated,
+}
+
+impl InputProblem {
+    /// Whether the problem means the text can't be an input at all. Fetched text with such a
+    /// problem is not cached
+    pub fn is_fatal(self) -> bool {
+        matches!(self, Self::Empty | Self::Html)
+    }
+}
+
+impl fmt::Display for InputProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Empty => "it is empty",
+            Self::Html => "it is an HTML page",
+            Self::Unterminated => "it does not end with a newline",
+        })
+    }
+}
+
+/// Every problem with `input`
+pub(crate) fn problems(input: &str) -> Vec<InputProblem> {
+    let trimmed = input.trim_start();
+    if trimmed.is_empty() {
+        return vec![InputProblem::Empty];
+    }
+
+    let mut problems = Vec::new();
+    let starts_with = |prefix: &str| {
+        trimmed
+            .as_bytes()
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix.as_bytes()))
+    };
+    if starts_with("<!doctype html") || starts_with("<html") {
+        problems.push(InputProblem::Html);
+    }
+    if !input.ends_with('\n') {
+        problems.push(InputProblem::Unterminated);
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problems() {
+        assert_eq!(super::problems("1\n2\n"), []);
+        assert_eq!(super::problems("<>\n"), []);
+        assert_eq!(super::problems(" \n"), [InputProblem::Empty]);
+        assert_eq!(super::problems("1\n2"), [InputProblem::Unterminated]);
+        assert_eq!(
+            super::problems("<!DOCTYPE html>\n<html lang=\"en-us\"></html>"),
+            [InputProblem::Html, InputProblem::Unterminated]
+        );
+    }
+}